@@ -1,4 +1,14 @@
-use std::{env::var_os, fs, path::Path, process::Command};
+use std::{
+    env::var_os,
+    fs,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::Command,
+};
+
+#[allow(dead_code)]
+#[path = "src/game/dawg.rs"]
+mod dawg;
 
 fn main() {
     let commit_hash = String::from_utf8(
@@ -18,7 +28,18 @@ fn main() {
         format!(r#"pub const COMMIT_HASH: &str = "{commit_hash}";"#),
     )
     .unwrap();
+
+    // Build the dictionary ahead of time so the server doesn't have to at startup
+    let words =
+        BufReader::new(zstd::Decoder::new(fs::File::open("words.txt.zst").unwrap()).unwrap())
+            .lines()
+            .map(Result::unwrap);
+    let dawg = dawg::Dawg::from_words(words);
+    fs::write(Path::new(&out_dir).join("words.dawg"), dawg.as_bytes()).unwrap();
+
     println!("cargo::rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-changed=src/game/dawg.rs");
+    println!("cargo::rerun-if-changed=words.txt.zst");
     println!("cargo::rerun-if-changed=.git/refs/heads");
     println!("cargo::rerun-if-changed=.git/HEAD");
 }
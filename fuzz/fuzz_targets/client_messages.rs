@@ -0,0 +1,52 @@
+//! Feeds client messages to a table the way the server does, checking that nothing panics and
+//! that no tiles go missing. Each line of the input is a seat number from 0 to 3 and a
+//! `ClientMessage` as JSON, such as `0 "StartGame"` or `1 {"JoinWithName":"P1"}`.
+//! Lines that don't parse are skipped. The host is always P0, and the seed corpus in
+//! `corpus/client_messages/seed-game` plays a short game that a unit test in `table.rs` checks.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scrabble::{
+    game::Game,
+    table::{Rules, Table},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(mut table) = Table::new("P0".into(), Rules::default(), false) else {
+        return;
+    };
+    // A fixed seed deals the same racks every run, so that the seed corpus can play real moves
+    // and crashes can be reproduced
+    table.game = Game::with_seed(0);
+    table.game.add_player("P0");
+    for line in input.lines() {
+        let Some((seat, json)) = line.split_once(' ') else {
+            continue;
+        };
+        let (Ok(seat), Ok(msg)) = (seat.parse::<u8>(), serde_json::from_str(json)) else {
+            continue;
+        };
+        // Like a connection, which only has a name once it has taken a seat
+        let name = format!("P{}", seat % 4);
+        let name = table.game.has_player(&name).then_some(name.as_str());
+        // Errors are sent back to the client, only panics are bugs
+        let _ = table.handle(name, msg);
+        check_tiles(&table);
+    }
+});
+
+/// The bag, racks and board always hold the 100 tiles of a game between them
+fn check_tiles(table: &Table) {
+    let game = &table.game;
+    let board = game.board();
+    let on_board = (0..15)
+        .flat_map(|x| (0..15).map(move |y| (x, y)))
+        .filter(|&(x, y)| board[x][y].is_some())
+        .count();
+    let on_racks: usize = game.players().iter().map(|p| p.tiles().len()).sum();
+    assert_eq!(on_board + on_racks + game.tiles_in_bag(), 100);
+}
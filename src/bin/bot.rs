@@ -0,0 +1,320 @@
+//! Load testing: `bot <connections> [seats] [ws://host:2222/]` opens that many connections,
+//! seats them at tables of `seats` players and plays every game to the end, each bot playing
+//! its highest scoring move. Then it reports how long table updates took to reach each player.
+//! A player busy with one update may skip straight to a later one, but should never see a table
+//! go back to an older version, and every player should see their game finish. Exits with an
+//! error if not.
+
+mod version {
+    include!(concat!(env!("OUT_DIR"), "/version.rs"));
+}
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use futures_util::{SinkExt, StreamExt};
+use scrabble::{
+    game::solve,
+    protocol::{ClientHello, ClientMessage, ServerUpdate, TableView, PROTOCOL_VERSION},
+    table::{GameState, Rules, TableId},
+};
+use tokio::{sync::watch, task::JoinSet, time::timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use version::COMMIT_HASH;
+
+/// Every game has to finish within this long
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let connections: usize = args
+        .first()
+        .ok_or_else(|| anyhow!("Usage: bot <connections> [seats] [url]"))?
+        .parse()?;
+    let seats: usize = args.get(1).map_or(Ok(2), |s| s.parse())?;
+    let url = args
+        .get(2)
+        .cloned()
+        .unwrap_or_else(|| "ws://localhost:2222/".into());
+    ensure!((2..=4).contains(&seats), "Tables are for 2 to 4 players");
+    let tables = connections / seats;
+    ensure!(tables > 0, "Need at least {seats} connections for a table");
+    println!(
+        "Playing {tables} games of {seats} players on {url}{}",
+        match connections % seats {
+            0 => String::new(),
+            n => format!(", leaving {n} connections unused"),
+        }
+    );
+
+    let start = Instant::now();
+    let mut bots = JoinSet::new();
+    let mut logs = vec![];
+    for t in 0..tables {
+        let log = Arc::new(TableLog::default());
+        logs.push(log.clone());
+        let (id_send, id_recv) = watch::channel(None);
+        let mut id_send = Some(id_send);
+        for seat in 0..seats {
+            let bot = Bot {
+                name: format!("bot{t}-{seat}"),
+                seats,
+                id_send: id_send.take(),
+                id_recv: id_recv.clone(),
+                log: log.clone(),
+                version: 0,
+                received: vec![],
+                invalid_moves: 0,
+            };
+            let url = url.clone();
+            bots.spawn(async move {
+                let name = bot.name.clone();
+                let res = timeout(TIMEOUT, bot.run(&url)).await;
+                (t, name, res.unwrap_or_else(|_| Err(anyhow!("Timed out"))))
+            });
+        }
+    }
+
+    let mut report = Report::default();
+    while let Some(res) = bots.join_next().await {
+        let (t, name, res) = res?;
+        match res {
+            Ok(bot) => report.add(t, &bot, &logs[t]),
+            Err(e) => report.errors.push(format!("{name}: {e}")),
+        }
+    }
+    report.print(start.elapsed());
+    ensure!(report.errors.is_empty(), "Some players failed");
+    Ok(())
+}
+
+/// Every game state at a table, by when the action that led to it was sent.
+/// Shared by the bots playing at the table.
+#[derive(Default)]
+struct TableLog(Mutex<Turns>);
+
+#[derive(Default)]
+struct Turns {
+    /// When the latest action was sent, until some player receives the state it led to
+    pending: Option<Instant>,
+    sent: HashMap<u64, Instant>,
+}
+
+impl TableLog {
+    fn action_sent(&self) {
+        self.0.lock().unwrap().pending = Some(Instant::now());
+    }
+
+    /// When the action that led to `state` was sent
+    fn state_received(&self, state: u64) -> Option<Instant> {
+        let mut turns = self.0.lock().unwrap();
+        if let Some(sent) = turns.sent.get(&state) {
+            return Some(*sent);
+        }
+        let sent = turns.pending.take()?;
+        turns.sent.insert(state, sent);
+        Some(sent)
+    }
+}
+
+struct Bot {
+    name: String,
+    seats: usize,
+    /// The table's host creates it and sends its id to the other players
+    id_send: Option<watch::Sender<Option<TableId>>>,
+    id_recv: watch::Receiver<Option<TableId>>,
+    log: Arc<TableLog>,
+    /// Latest version of the table received
+    version: u64,
+    /// Each game state received and how long after its action was sent
+    received: Vec<(u64, Option<Duration>)>,
+    invalid_moves: usize,
+}
+
+impl Bot {
+    async fn run(mut self, url: &str) -> Result<Bot> {
+        let (mut ws, _) = connect_async(url).await?;
+        macro_rules! send {
+            ($msg:expr) => {
+                ws.send(Message::Text(serde_json::to_string(&$msg)?))
+                    .await?
+            };
+        }
+
+        send!(ClientMessage::Hello(ClientHello {
+            protocol: PROTOCOL_VERSION,
+            build: Some(COMMIT_HASH.into()),
+        }));
+        let id_send = self.id_send.take();
+        if id_send.is_some() {
+            send!(ClientMessage::CreateTable {
+                name: self.name.clone(),
+                rules: Rules {
+                    seats: self.seats,
+                    word_lookups: false,
+                },
+                private: false,
+            });
+        } else {
+            let id = self.id_recv.wait_for(Option::is_some).await?.unwrap();
+            send!(ClientMessage::JoinTable(id));
+            send!(ClientMessage::JoinWithName(self.name.clone()));
+        }
+
+        let mut started = false;
+        let mut acted_on = None;
+        loop {
+            let update = match ws.next().await {
+                Some(Ok(Message::Text(json))) => serde_json::from_str(&json)?,
+                Some(Ok(Message::Close(frame))) => bail!("Server closed the connection: {frame:?}"),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => bail!(e),
+                None => bail!("Server closed the connection"),
+            };
+            let table = match update {
+                ServerUpdate::Table(table) => table,
+                ServerUpdate::InvalidMove(im) => {
+                    // The move generator and the server disagree, so pass instead
+                    println!("{}: invalid move: {}", self.name, im.explanation);
+                    self.invalid_moves += 1;
+                    self.log.action_sent();
+                    send!(ClientMessage::ExchangeTiles);
+                    continue;
+                }
+                ServerUpdate::Error(e) => bail!("Server error {}: {}", e.code, e.message),
+                _ => continue,
+            };
+            if let Some(id_send) = &id_send {
+                id_send.send_replace(Some(table.id));
+            }
+            ensure!(
+                table.version >= self.version,
+                "Received version {} of the table after version {}",
+                table.version,
+                self.version
+            );
+            self.version = table.version;
+
+            match table.state {
+                GameState::Setup => {
+                    let full = table.game.players.len() == self.seats;
+                    if id_send.is_some() && full && !started {
+                        started = true;
+                        self.log.action_sent();
+                        send!(ClientMessage::StartGame);
+                    }
+                    continue;
+                }
+                GameState::Running | GameState::Finished => {}
+            }
+
+            let state = fingerprint(&table);
+            if self.received.last().map(|(s, _)| *s) != Some(state) {
+                let sent = self.log.state_received(state);
+                self.received.push((state, sent.map(|s| s.elapsed())));
+            }
+            if table.state == GameState::Finished {
+                ws.close(None).await?;
+                return Ok(self);
+            }
+
+            let game = &table.game;
+            let my_turn = game.players[game.whose_turn].name() == self.name;
+            if my_turn && acted_on != Some(state) {
+                acted_on = Some(state);
+                let rack = game.players[game.whose_turn].tiles();
+                let best = solve::generate_moves(&game.board, rack)
+                    .into_iter()
+                    .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)));
+                self.log.action_sent();
+                match best {
+                    Some((m, _)) => send!(ClientMessage::PlayMove(m)),
+                    // Exchanging with an empty bag changes nothing, and the game would never end
+                    None if game.tiles_in_bag == 0 => {
+                        send!(ClientMessage::ForfeitPlayer(self.name.clone()))
+                    }
+                    None => send!(ClientMessage::ExchangeTiles),
+                }
+            }
+        }
+    }
+}
+
+/// Identifies a game state, so that a player can tell which updates it has seen
+fn fingerprint(table: &TableView) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", table.game).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Default)]
+struct Report {
+    games: HashSet<usize>,
+    latencies: Vec<Duration>,
+    /// Game states that players skipped over, having received a later one first
+    skipped: usize,
+    invalid_moves: usize,
+    errors: Vec<String>,
+}
+
+impl Report {
+    fn add(&mut self, table: usize, bot: &Bot, log: &TableLog) {
+        self.games.insert(table);
+        self.latencies
+            .extend(bot.received.iter().filter_map(|(_, l)| *l));
+        self.invalid_moves += bot.invalid_moves;
+
+        let received: HashSet<u64> = bot.received.iter().map(|(s, _)| *s).collect();
+        self.skipped += log
+            .0
+            .lock()
+            .unwrap()
+            .sent
+            .keys()
+            .filter(|s| !received.contains(s))
+            .count();
+    }
+
+    fn print(&mut self, elapsed: Duration) {
+        println!(
+            "{} games finished in {:.1}s",
+            self.games.len(),
+            elapsed.as_secs_f64()
+        );
+
+        self.latencies.sort();
+        let percentile = |p: usize| {
+            let i = (self.latencies.len() * p / 100).min(self.latencies.len() - 1);
+            self.latencies[i].as_secs_f64() * 1000.0
+        };
+        if !self.latencies.is_empty() {
+            println!(
+                "{} table updates, latency in ms: median {:.1}, p90 {:.1}, p99 {:.1}, max {:.1}",
+                self.latencies.len(),
+                percentile(50),
+                percentile(90),
+                percentile(99),
+                percentile(100),
+            );
+        }
+
+        if self.invalid_moves > 0 {
+            println!("{} moves were rejected by the server", self.invalid_moves);
+        }
+        if self.skipped > 0 {
+            println!(
+                "{} game states were skipped by players still busy with an earlier one",
+                self.skipped
+            );
+        }
+        for e in &self.errors {
+            println!("{e}");
+        }
+    }
+}
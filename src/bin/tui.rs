@@ -0,0 +1,278 @@
+//! A terminal client for the server, for playing over ssh: `tui [ws://host:2222/]`.
+//! Commands are typed one per line, see `help`.
+
+mod version {
+    include!(concat!(env!("OUT_DIR"), "/version.rs"));
+}
+
+use anyhow::{anyhow, bail, Result};
+use futures_util::{SinkExt, StreamExt};
+use scrabble::{
+    game::{
+        solve::{Modifier, MODIFIERS},
+        Board, Move,
+    },
+    protocol::{ClientHello, ClientMessage, ServerUpdate, TableView, PROTOCOL_VERSION},
+    table::{GameState, Rules},
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use version::COMMIT_HASH;
+
+const HELP: &str = "\
+Commands:
+  tables                  list open games
+  create NAME [SEATS]     host a game
+  join ID NAME            join an open game
+  invite CODE NAME        join a private game
+  start                   start the game (host only)
+  8H WORD, H8 WORD        play WORD across from 8H, or down from H8
+  H8 across WORD          play with an explicit direction, 'across' or 'down'
+  exchange                exchange all your tiles
+  check WORD              look a word up in the dictionary
+  unseen                  list the tiles you haven't seen
+  board                   show the board again
+  leave                   leave the table
+  quit
+
+Letters already on the board can be typed or written as '.', and blanks are lowercase.";
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let url = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "ws://localhost:2222/".into());
+    let (mut ws, _) = connect_async(&url).await?;
+    println!("Connected to {url}. Type 'help' for commands.");
+    for msg in [
+        ClientMessage::Hello(ClientHello {
+            protocol: PROTOCOL_VERSION,
+            build: Some(COMMIT_HASH.into()),
+        }),
+        ClientMessage::UpdateMe,
+    ] {
+        ws.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+    }
+
+    let mut client = Client::default();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        tokio::select! {
+            msg = ws.next() => match msg {
+                Some(Ok(Message::Text(json))) => client.receive(serde_json::from_str(&json)?),
+                Some(Ok(Message::Close(_))) | None => bail!("Server closed the connection"),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => bail!(e),
+            },
+
+            line = lines.next_line() => {
+                let Some(line) = line? else { break };
+                let line = line.trim();
+                if line == "quit" {
+                    break;
+                }
+                match client.command(line) {
+                    Ok(msgs) => {
+                        for msg in msgs {
+                            ws.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                        }
+                    }
+                    Err(e) => println!("{e}"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Client {
+    table: Option<TableView>,
+    name: Option<String>,
+}
+
+impl Client {
+    /// The messages to send for a line the user typed
+    fn command(&mut self, line: &str) -> Result<Vec<ClientMessage>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let msg = match words[..] {
+            [] => return Ok(vec![]),
+            ["help"] => {
+                println!("{HELP}");
+                return Ok(vec![]);
+            }
+            ["board"] => {
+                let table = self
+                    .table
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Not at a table"))?;
+                self.show_table(table);
+                return Ok(vec![]);
+            }
+            ["tables"] => ClientMessage::UpdateMe,
+            ["create", name] => self.create(name, "4")?,
+            ["create", name, seats] => self.create(name, seats)?,
+            ["join", id, name] => {
+                self.name = Some(name.into());
+                return Ok(vec![
+                    ClientMessage::JoinTable(id.parse()?),
+                    ClientMessage::JoinWithName(name.into()),
+                ]);
+            }
+            ["invite", code, name] => {
+                self.name = Some(name.into());
+                return Ok(vec![
+                    ClientMessage::JoinWithInvite(code.into()),
+                    ClientMessage::JoinWithName(name.into()),
+                ]);
+            }
+            ["start"] => ClientMessage::StartGame,
+            ["exchange"] => ClientMessage::ExchangeTiles,
+            ["check", word] => ClientMessage::CheckWord(word.into()),
+            ["unseen"] => ClientMessage::UnseenTiles,
+            ["leave"] => {
+                self.name = None;
+                ClientMessage::LeaveTable
+            }
+            _ => {
+                let table = self
+                    .table
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Unknown command, try 'help'"))?;
+                ClientMessage::PlayMove(
+                    Move::parse(&table.game.board, line).map_err(|e| anyhow!(e))?,
+                )
+            }
+        };
+        Ok(vec![msg])
+    }
+
+    fn create(&mut self, name: &str, seats: &str) -> Result<ClientMessage> {
+        self.name = Some(name.into());
+        Ok(ClientMessage::CreateTable {
+            name: name.into(),
+            rules: Rules {
+                seats: seats.parse()?,
+                word_lookups: false,
+            },
+            private: false,
+        })
+    }
+
+    fn receive(&mut self, update: ServerUpdate) {
+        match update {
+            ServerUpdate::Hello(hello) if hello.build != COMMIT_HASH => {
+                println!(
+                    "The server is running build {}, this is {COMMIT_HASH}",
+                    hello.build
+                );
+            }
+            ServerUpdate::Hello(_) => {}
+            ServerUpdate::Table(table) => {
+                self.show_table(&table);
+                self.table = Some(*table);
+            }
+            ServerUpdate::Lobby(listings) => {
+                self.table = None;
+                if listings.is_empty() {
+                    println!("No open games");
+                }
+                for l in listings {
+                    let seats = l.players.len() + l.reserved_seats;
+                    println!(
+                        "#{} {}'s game ({seats}/{} seats)",
+                        l.id, l.host, l.rules.seats
+                    );
+                }
+            }
+            ServerUpdate::Seat(_) => {}
+            ServerUpdate::InvalidMove(im) => println!("Invalid move: {}", im.explanation),
+            ServerUpdate::WordCheck(c) if c.valid => println!("{} is a word", c.word),
+            ServerUpdate::WordCheck(c) => println!("{} is not a word", c.word),
+            ServerUpdate::WordSearch(s) => println!("{}", s.words.join(" ")),
+            ServerUpdate::UnseenTiles(u) => {
+                let counts: Vec<String> = u.counts.iter().map(|(t, n)| format!("{t}{n}")).collect();
+                println!("Unseen: {} ({} in the bag)", counts.join(" "), u.in_bag);
+            }
+            ServerUpdate::TileOdds(o) => println!("{:.1}%", o.chance * 100.0),
+            ServerUpdate::Error(e) => println!("Error: {e}"),
+        }
+    }
+
+    fn show_table(&self, table: &TableView) {
+        let game = &table.game;
+        match table.state {
+            GameState::Setup => {
+                let players: Vec<&str> = game.players.iter().map(|p| p.name()).collect();
+                println!(
+                    "Table #{}, waiting to start: {} ({}/{} seats)",
+                    table.id,
+                    players.join(", "),
+                    players.len(),
+                    table.rules.seats
+                );
+                if let Some(code) = &table.invite_code {
+                    println!("Invite code: {code}");
+                }
+                return;
+            }
+            GameState::Running | GameState::Finished => {}
+        }
+
+        print!("{}", render_board(&game.board));
+        for (i, p) in game.players.iter().enumerate() {
+            let marker = if i == game.whose_turn && !game.finished {
+                ">"
+            } else {
+                " "
+            };
+            let forfeited = if p.has_forfeited() {
+                " (forfeited)"
+            } else {
+                ""
+            };
+            println!("{marker} {:<16} {:>4}{forfeited}", p.name(), p.score());
+        }
+        if let Some(me) = game
+            .players
+            .iter()
+            .find(|p| Some(p.name()) == self.name.as_deref())
+        {
+            let rack: Vec<String> = me.tiles().iter().map(ToString::to_string).collect();
+            println!("Your rack: {}", rack.join(" "));
+        }
+        if game.finished {
+            println!("The game is over");
+        } else {
+            println!("{} tiles in the bag", game.tiles_in_bag);
+        }
+    }
+}
+
+/// The board with coordinates and coloured premium squares
+fn render_board(board: &Board) -> String {
+    let mut out = String::from("   ");
+    for column in b'A'..=b'O' {
+        out += &format!(" {} ", column as char);
+    }
+    out += "\n";
+    for x in 0..15 {
+        out += &format!("{:>2} ", x + 1);
+        for y in 0..15 {
+            let (color, label) = match (board[x][y], MODIFIERS.get(&(x, y))) {
+                (Some(t), _) => ("30;43", format!(" {t} ")),
+                (None, Some(Modifier::TripleWord)) => ("41", "3W ".into()),
+                (None, Some(Modifier::DoubleWord)) => ("45", "2W ".into()),
+                (None, Some(Modifier::TripleLetter)) => ("44", "3L ".into()),
+                (None, Some(Modifier::DoubleLetter)) => ("46", "2L ".into()),
+                (None, None) => {
+                    out += " . ";
+                    continue;
+                }
+            };
+            out += &format!("\x1b[{color}m{label}\x1b[0m");
+        }
+        out += "\n";
+    }
+    out
+}
@@ -1,3 +1,5 @@
+//! The rules of the game: tiles, the board, moves and their validation, and players taking turns.
+//!
 //! Sources:
 //! - <https://hasbro-new.custhelp.com/app/answers/detail/a_id/55/~/what-is-the-total-face-value-of-all-the-scrabble-tiles%3F>
 //! - <https://hasbro-new.custhelp.com/app/answers/detail/a_id/19/related/1>
@@ -5,10 +7,17 @@
 //! - <https://github.com/dwyl/english-words>
 //! - <https://www.hasbro.com/common/instruct/Scrabble_(2003).pdf>
 
+pub mod analysis;
+pub mod dawg;
+pub mod endgame;
+pub mod equity;
 pub mod solve;
+pub mod unseen;
 
 use forr::forr;
-use rand::seq::SliceRandom;
+use itertools::Itertools;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
@@ -16,11 +25,13 @@ use std::{
     fmt::Display,
     iter,
     ops::{Index, IndexMut},
+    str::FromStr,
 };
 
 macro_rules! tile_enum {
     ( $(#[$attr:meta])* $name:ident { $(A = $a:expr,)? $(Blank $($blank:tt)+)? }) => {
         $(#[$attr])*
+        #[allow(missing_docs)]
         pub enum $name {
             A $(= $a)?, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
             $(Blank $($blank)+)?
@@ -33,10 +44,19 @@ tile_enum! {
     /// It has the same repr as the corresponding lowercase ascii char.
     #[repr(u8)]
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+    #[cfg_attr(test, derive(ts_rs::TS), ts(export_to = "protocol.ts", rename = "LetterT"))]
     Letter { A = b'a', }
 }
 
 impl Letter {
+    fn from_ascii(c: u8) -> Self {
+        if !c.is_ascii_lowercase() {
+            panic!("Invalid ascii byte for letter: {c}");
+        }
+
+        unsafe { std::mem::transmute(c) }
+    }
+
     fn as_ascii(self) -> u8 {
         self as u8
     }
@@ -51,6 +71,7 @@ tile_enum! {
     /// Like `Letter`, it is repr'd by corresponding ascii chars, while Blank is repr'd as `b'*'`.
     #[repr(u8)]
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[cfg_attr(test, derive(ts_rs::TS), ts(export_to = "protocol.ts", rename = "TileT"))]
     Tile {
         A = b'a',
         Blank = b'*'
@@ -66,7 +87,6 @@ impl Tile {
         char::from(self.as_ascii())
     }
 
-    #[cfg(test)]
     fn from_ascii(c: u8) -> Self {
         if !(c.is_ascii_lowercase() || c == b'*') {
             panic!("Invalid ascii byte for tile: {c}");
@@ -94,8 +114,19 @@ impl Tile {
         }
     }
 
+    /// Tiles written as letters in either case, with `?` for a blank, eg "AEINRST?"
+    pub fn parse_rack(s: &str) -> Result<Vec<Tile>, String> {
+        s.chars()
+            .map(|c| match c {
+                '?' => Ok(Tile::Blank),
+                c if c.is_ascii_alphabetic() => Ok(Tile::from_ascii(c.to_ascii_lowercase() as u8)),
+                c => Err(format!("Invalid tile: {c}")),
+            })
+            .collect()
+    }
+
     /// Point value according to official Scrabble rules
-    fn point_value(self) -> u32 {
+    pub fn point_value(self) -> u32 {
         use Tile::*;
         match self {
             A | E | I | O | U | L | N | S | T | R => 1,
@@ -135,12 +166,22 @@ impl Tile {
 tile_enum! {
     /// Enum for tiles on the board. Blank tiles hold the `Letter` that they represent on the board.
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+    #[cfg_attr(test, derive(ts_rs::TS), ts(export_to = "protocol.ts", rename = "BoardTileT"))]
     BoardTile {
         Blank(Letter)
     }
 }
 
 impl BoardTile {
+    /// A tile from how it's written in move notation: uppercase for a letter, lowercase for a blank
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'A'..='Z' => Some(Tile::from_ascii(c.to_ascii_lowercase() as u8).into()),
+            'a'..='z' => Some(BoardTile::Blank(Letter::from_ascii(c as u8))),
+            _ => None,
+        }
+    }
+
     fn as_tile(self) -> Tile {
         forr! { $letter:tt in [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z] $:
             match self {
@@ -160,7 +201,6 @@ impl BoardTile {
     }
 }
 
-#[cfg(test)]
 impl From<Tile> for BoardTile {
     fn from(t: Tile) -> Self {
         forr! { $letter:tt in [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z] $:
@@ -176,6 +216,11 @@ impl From<Tile> for BoardTile {
 /// `Move`s are not inherently valid - tiles in the move could overlap ones already on the board,
 /// overlap each other, be at invalid coordinates, contain letters the player doesn't have, etc.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "MoveT")
+)]
 pub struct Move {
     tiles: Vec<(Position, BoardTile)>,
 
@@ -185,6 +230,7 @@ pub struct Move {
 }
 
 impl Move {
+    /// A move placing each tile at its position, in any order
     pub fn new(tiles: Vec<(Position, BoardTile)>) -> Self {
         let mut m = Self {
             tiles,
@@ -194,6 +240,73 @@ impl Move {
         m
     }
 
+    /// Read a move like `8H WORD`, `H8 WORD` or `H8 across WORD`. The row first means across and the
+    /// column first means down, unless a direction is given. Blanks are lowercase, and squares already
+    /// filled on `board` are skipped, whether their letter is typed or written as `.`. A `.` on an
+    /// empty square leaves a gap.
+    pub fn parse(board: &Board, s: &str) -> Result<Move, String> {
+        let (coordinates, direction, word) = match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [c, w] => (c, None, w),
+            [c, d, w] => (c, Some(d), w),
+            _ => return Err(format!("Expected a move like 8H WORD, got {s:?}")),
+        };
+
+        let invalid_coordinates = || format!("Invalid coordinates: {coordinates}");
+        if !coordinates.is_ascii() {
+            return Err(invalid_coordinates());
+        }
+        let upper = coordinates.to_ascii_uppercase();
+        let (row, column, row_first) = match upper.strip_suffix(|c: char| c.is_ascii_uppercase()) {
+            Some(row) => (row, upper.as_bytes()[upper.len() - 1], true),
+            None => (&upper[1..], upper.as_bytes()[0], false),
+        };
+        let x = row.parse::<usize>().ok().filter(|r| (1..=15).contains(r));
+        let y = (b'A'..=b'O')
+            .contains(&column)
+            .then(|| (column - b'A') as usize);
+        let (Some(x), Some(y)) = (x, y) else {
+            return Err(invalid_coordinates());
+        };
+        let across = match direction {
+            None => row_first,
+            Some("across") => true,
+            Some("down") => false,
+            Some(d) => return Err(format!("Invalid direction: {d}")),
+        };
+
+        let mut tiles = vec![];
+        let mut pos = (x - 1, y);
+        for c in word.chars() {
+            if pos.0 >= 15 || pos.1 >= 15 {
+                return Err(format!("{word} doesn't fit on the board"));
+            }
+            match board[pos.0][pos.1] {
+                Some(t) if c == '.' || t.as_letter().as_char() == c.to_ascii_lowercase() => {}
+                Some(t) => return Err(format!("{t} is already on the board where {c} would go")),
+                None if c == '.' => {}
+                None => {
+                    let t =
+                        BoardTile::from_char(c).ok_or_else(|| format!("Invalid letter: {c}"))?;
+                    tiles.push((pos, t));
+                }
+            }
+            pos = if across {
+                (pos.0, pos.1 + 1)
+            } else {
+                (pos.0 + 1, pos.1)
+            };
+        }
+        if tiles.is_empty() {
+            return Err("That doesn't place any tiles".into());
+        }
+        Ok(Move::new(tiles))
+    }
+
+    /// The tiles placed by this move and where they go
+    pub fn tiles(&self) -> &[(Position, BoardTile)] {
+        &self.tiles
+    }
+
     fn crosses_center(&self) -> bool {
         self.tiles.iter().any(|(p, _)| *p == (7, 7))
     }
@@ -220,7 +333,7 @@ impl Move {
         self.is_vertical() || self.is_horizontal()
     }
 
-    fn sorted(&self) -> Cow<Move> {
+    fn sorted(&self) -> Cow<'_, Move> {
         if self.sorted {
             Cow::Borrowed(self)
         } else {
@@ -245,9 +358,36 @@ impl Move {
 }
 
 /// A player-facing message that explains why a move is invalid, along with a set of relevant board positions.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "InvalidMoveT")
+)]
 pub struct InvalidMove {
+    /// What's wrong with the move, for showing to the player
     pub explanation: String,
+    /// Squares to highlight, such as the tiles making up a word that isn't in the dictionary
+    pub positions: Vec<Position>,
+    /// Empty squares that break up a move that isn't contiguous
+    pub gaps: Vec<Position>,
+    /// Every non-word the move would form, not just the first one found
+    pub invalid_words: Vec<InvalidWord>,
+    /// For single-tile moves, which way the main word was read
+    pub axis: Option<Axis>,
+}
+
+/// A string of tiles formed by a move that isn't in the dictionary.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "InvalidWordT")
+)]
+pub struct InvalidWord {
+    /// The tiles as read, in uppercase
+    pub word: String,
+    /// The squares the word covers
     pub positions: Vec<Position>,
 }
 
@@ -256,23 +396,62 @@ impl InvalidMove {
         Self {
             explanation: explanation.into(),
             positions: positions.into_iter().collect(),
+            gaps: vec![],
+            invalid_words: vec![],
+            axis: None,
         }
     }
 }
 
 /// A move that a player previously played, along with the new words it introduced and their point values.
 /// The value of the whole move is the sum of the words' values.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "PlayedMoveT")
+)]
 pub struct PlayedMove {
     original_move: Move,
     word_values: Vec<(String, u32)>,
 }
 
+impl PlayedMove {
+    /// Points the move scored
+    pub fn value(&self) -> u32 {
+        self.word_values.iter().map(|(_, v)| v).sum()
+    }
+
+    /// Each word the move formed and its value
+    pub fn word_values(&self) -> &[(String, u32)] {
+        &self.word_values
+    }
+}
+
 /// A position on the board.
 pub type Position = (usize, usize);
 
+/// The direction a word is read in. Horizontal words share an x coordinate.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "AxisT")
+)]
+pub enum Axis {
+    /// Read from left to right
+    Horizontal,
+    /// Read from top to bottom
+    Vertical,
+}
+
 /// A game board, a 15x15 array of optional `BoardTile`s
-#[derive(Clone, Copy, Debug, Default, Serialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "BoardT")
+)]
 pub struct Board([[Option<BoardTile>; 15]; 15]);
 
 impl Board {
@@ -292,20 +471,27 @@ impl Board {
         *self == Board::new()
     }
 
-    fn play_move(&mut self, m: &Move) -> Result<PlayedMove, InvalidMove> {
+    /// Place a move's tiles if it's valid, returning the words it formed and what they scored
+    pub fn play_move(&mut self, m: &Move) -> Result<PlayedMove, InvalidMove> {
         solve::validate_move(self, m)?;
-        let (expanded_move, crossing_moves) = solve::expand_move(self, m);
+
+        let gaps = solve::find_gaps(self, m);
+        if !gaps.is_empty() {
+            return Err(InvalidMove {
+                gaps: gaps.clone(),
+                ..InvalidMove::new("That move is not contiguous", gaps)
+            });
+        }
+
+        let (expanded_move, crossing_moves, axis) = solve::expand_move(self, m);
+        let axis = (m.tiles.len() == 1).then_some(axis);
 
         if expanded_move.tiles.len() == m.tiles.len()
             && crossing_moves.is_empty()
             && !self.is_empty()
         {
-            return Err(InvalidMove::new("That move is disconnected", m.positions()));
-        }
-
-        if !m.positions().all(|p| expanded_move.contains_position(p)) {
             return Err(InvalidMove::new(
-                "That move is not contiguous",
+                "That move doesn't connect to any tiles on the board",
                 m.positions(),
             ));
         }
@@ -320,13 +506,42 @@ impl Board {
 
         let new_board = self.with_move_applied(m);
 
-        for (m, word, _score) in &moves {
-            if !solve::is_word(word) {
-                return Err(InvalidMove::new(
-                    format!("'{}' is not a word", word.to_ascii_uppercase()),
-                    m.positions(),
-                ));
+        let invalid_words: Vec<_> = moves
+            .iter()
+            .filter(|(_, word, _)| !solve::is_word(word))
+            .map(|(m, word, _)| InvalidWord {
+                word: word.to_ascii_uppercase(),
+                positions: m.positions().collect(),
+            })
+            .collect();
+
+        if !invalid_words.is_empty() {
+            let quoted: Vec<_> = invalid_words
+                .iter()
+                .map(|w| format!("'{}'", w.word))
+                .collect();
+            let mut explanation = match quoted.as_slice() {
+                [one] => format!("{one} is not a word"),
+                [init @ .., last] => format!("{} and {last} are not words", init.join(", ")),
+                [] => unreachable!(),
+            };
+            if let Some(axis) = axis {
+                let direction = match axis {
+                    Axis::Horizontal => "across",
+                    Axis::Vertical => "down",
+                };
+                explanation += &format!(" (reading {direction})");
             }
+            let positions = invalid_words
+                .iter()
+                .flat_map(|w| w.positions.iter().copied())
+                .unique()
+                .collect::<Vec<_>>();
+            return Err(InvalidMove {
+                invalid_words,
+                axis,
+                ..InvalidMove::new(explanation, positions)
+            });
         }
 
         let word_values = moves
@@ -343,7 +558,12 @@ impl Board {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "GameEndT")
+)]
 enum GameEnd {
     /// The player did not play the last move, and so has some remaining tiles that they lose points for.
     RemainingTiles(Vec<Tile>),
@@ -351,21 +571,54 @@ enum GameEnd {
     PlayedLastMove(u32),
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TurnT")
+)]
 enum Turn {
     PlayedMove(PlayedMove),
     TilesExchanged,
+    /// The player left the game. Their tiles went back into the bag and their turns are skipped.
+    Forfeited,
     GameEnd(GameEnd),
 }
 
-#[derive(Default, Clone, Debug, Serialize)]
+impl Turn {
+    /// Points gained or lost on this turn
+    fn value(&self) -> i32 {
+        match self {
+            Turn::PlayedMove(pm) => pm.value() as i32,
+            Turn::TilesExchanged | Turn::Forfeited => 0,
+            Turn::GameEnd(GameEnd::RemainingTiles(ts)) => {
+                -(ts.iter().map(|t| t.point_value()).sum::<u32>() as i32)
+            }
+            Turn::GameEnd(GameEnd::PlayedLastMove(n)) => *n as i32,
+        }
+    }
+}
+
+/// Someone playing a game: their rack, and every turn they've taken.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "PlayerT")
+)]
 pub struct Player {
     name: String,
     tiles: Vec<Tile>,
     turns: Vec<Turn>,
+    forfeited: bool,
 }
 
 impl Player {
+    /// The name the player joined with, unique within their game
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     fn has_tiles_to_play_move(&self, m: &Move) -> bool {
         let mut player_tiles_count: HashMap<Tile, u8> = HashMap::with_capacity(self.tiles.len());
         for &t in &self.tiles {
@@ -388,9 +641,20 @@ impl Player {
         }
     }
 
-    // fn score(&self) -> u32 {
-    //     self.turns.iter().map(|pm| pm.value()).sum()
-    // }
+    /// The tiles on the player's rack
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Whether the player has left the game, see [`Game::forfeit`]
+    pub fn has_forfeited(&self) -> bool {
+        self.forfeited
+    }
+
+    /// Points from every move, adjusted by the tiles left on the racks once the game ends
+    pub fn score(&self) -> i32 {
+        self.turns.iter().map(Turn::value).sum()
+    }
 
     fn refill_tiles_from(&mut self, tile_bag: &mut Vec<Tile>) {
         while self.tiles.len() < 7 && !tile_bag.is_empty() {
@@ -399,46 +663,195 @@ impl Player {
     }
 }
 
+/// A game from setup to the end: the board, the bag, the players and whose turn it is.
+/// Moves are checked against the rules, so a game is always in a state the rules allow.
 #[derive(Clone, Debug, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "GameT")
+)]
 pub struct Game {
     board: Board,
+    /// Clients are only told how many tiles are left, not which
+    #[serde(rename = "tiles_in_bag", serialize_with = "serialize_len")]
+    #[cfg_attr(test, ts(as = "usize"))]
     tile_bag: Vec<Tile>,
     players: Vec<Player>,
     whose_turn: usize,
     finished: bool,
+
+    /// Seed of the game's random number generator. The same seed, players and actions
+    /// always produce the same game, see [`GameRecord`]. Never sent to clients, since it
+    /// would tell them the order of the tiles in the bag.
+    #[serde(skip)]
+    seed: u64,
+    #[serde(skip)]
+    rng: ChaCha8Rng,
+    /// Players in the order they joined, and whether they were shuffled when the game started
+    #[serde(skip)]
+    joined_players: Vec<String>,
+    #[serde(skip)]
+    shuffle_players: bool,
+    #[serde(skip)]
+    actions: Vec<Action>,
+
+    /// The game as it was before the most recent move or exchange, so that it can be taken back.
+    /// Clients only see whose turn that was.
+    #[serde(
+        rename = "last_turn_player",
+        serialize_with = "serialize_last_turn_player"
+    )]
+    #[cfg_attr(test, ts(as = "Option<String>"))]
+    previous: Option<Box<Game>>,
+}
+
+/// Everything needed to reproduce a game exactly.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GameRecord {
+    /// Seed the game's tiles were shuffled with
+    pub seed: u64,
+    /// Players in the order they joined
+    pub players: Vec<String>,
+    /// Whether the players were shuffled when the game started
+    pub shuffle_players: bool,
+    /// Every turn taken, in order
+    pub actions: Vec<Action>,
+}
+
+/// A turn taken in a game, as recorded in a [`GameRecord`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Action {
+    /// The player to move played this move
+    PlayMove(Move),
+    /// The player to move exchanged their whole rack
+    ExchangeTiles,
+    /// This player left the game, whether or not it was their turn
+    Forfeit(String),
+}
+
+/// Why a [`GameRecord`] couldn't be replayed.
+#[derive(Clone, Debug)]
+pub struct ReplayError {
+    /// Index of the action that failed, or `None` if the game couldn't be set up
+    pub action: Option<usize>,
+    /// What went wrong, such as why a move was invalid
+    pub explanation: String,
 }
 
 impl Game {
+    /// A game with no players yet and a random seed
     pub fn new() -> Self {
+        Game::with_seed(rand::random())
+    }
+
+    /// A game with no players yet, whose tiles will be drawn in an order decided by `seed`
+    pub fn with_seed(seed: u64) -> Self {
         Game {
             players: vec![],
             board: Board::default(),
             tile_bag: Tile::iter_game_count().collect(),
             whose_turn: 0,
             finished: false,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            joined_players: vec![],
+            shuffle_players: false,
+            actions: vec![],
+            previous: None,
         }
     }
 
-    pub fn start_game(&mut self) {
-        let rng = &mut rand::thread_rng();
-        self.tile_bag.shuffle(rng);
-        self.players.shuffle(rng);
+    /// Rebuild a game by playing back every action in a record.
+    pub fn replay(record: &GameRecord) -> Result<Game, ReplayError> {
+        Game::replay_with(record, |_, _| {})
+    }
+
+    /// Like [`Game::replay`], showing `visit` the game as it was before each action.
+    fn replay_with(
+        record: &GameRecord,
+        mut visit: impl FnMut(&Game, &Action),
+    ) -> Result<Game, ReplayError> {
+        let players = &record.players;
+        let setup_error = |explanation: &str| ReplayError {
+            action: None,
+            explanation: explanation.into(),
+        };
+        if players.len() < 2 {
+            return Err(setup_error("A game needs at least 2 players"));
+        }
+        if (1..players.len()).any(|i| players[..i].contains(&players[i])) {
+            return Err(setup_error("Every player needs a different name"));
+        }
+
+        let mut g = Game::with_seed(record.seed);
+        for p in players {
+            g.add_player(p.as_str());
+        }
+        g.start_game(record.shuffle_players);
+
+        for (i, action) in record.actions.iter().enumerate() {
+            let error = |explanation: String| ReplayError {
+                action: Some(i),
+                explanation,
+            };
+            if g.finished {
+                return Err(error("The game was already over".into()));
+            }
+            visit(&g, action);
+            match action {
+                Action::PlayMove(m) => g.play_move(m).map_err(|im| error(im.explanation))?,
+                Action::ExchangeTiles => g.exchange_tiles(),
+                Action::Forfeit(name) => {
+                    if !g.forfeit(name) {
+                        return Err(error(format!("{name} can't forfeit")));
+                    }
+                }
+            }
+        }
+        Ok(g)
+    }
+
+    /// The record of this game so far, which [`Game::replay`] turns back into this game.
+    pub fn record(&self) -> GameRecord {
+        GameRecord {
+            seed: self.seed,
+            players: self.joined_players.clone(),
+            shuffle_players: self.shuffle_players,
+            actions: self.actions.clone(),
+        }
+    }
+
+    /// Deal tiles to every player. Players take turns in the order they joined unless `shuffle_players` is set.
+    pub fn start_game(&mut self, shuffle_players: bool) {
+        self.joined_players = self.players.iter().map(|p| p.name.clone()).collect();
+        self.shuffle_players = shuffle_players;
+        self.tile_bag.shuffle(&mut self.rng);
+        if shuffle_players {
+            self.players.shuffle(&mut self.rng);
+        }
         for p in &mut self.players {
             p.refill_tiles_from(&mut self.tile_bag);
         }
     }
 
+    /// Play a move for the player whose turn it is, drawing them new tiles and passing the turn on.
+    /// If the move uses the last of their tiles with the bag empty, the game is over and they gain
+    /// the tiles left on everyone else's racks. Returns why the move isn't allowed otherwise.
     pub fn play_move(&mut self, m: &Move) -> Result<(), InvalidMove> {
         #[rustfmt::skip] macro_rules! player { () => { &mut self.players[self.whose_turn] }; }
 
-        if !player!().has_tiles_to_play_move(m) {
+        if !self.players[self.whose_turn].has_tiles_to_play_move(m) {
             return Err(InvalidMove::new(
                 "Tiles played that you don't have (impossible)",
                 vec![],
             ));
         }
 
+        let snapshot = self.snapshot();
         let played_move = self.board.play_move(m)?;
+        self.previous = Some(Box::new(snapshot));
+        self.actions.push(Action::PlayMove(m.clone()));
         player!().turns.push(Turn::PlayedMove(played_move));
         player!().remove_played_tiles(m);
         player!().refill_tiles_from(&mut self.tile_bag);
@@ -446,7 +859,7 @@ impl Game {
         if !self.finished && self.game_finished_by_tiles() {
             let mut gained_points = 0;
             for (i, p) in self.players.iter_mut().enumerate() {
-                if i != self.whose_turn {
+                if i != self.whose_turn && !p.forfeited {
                     let tiles = p.tiles.clone();
                     gained_points += tiles.iter().map(|t| t.point_value()).sum::<u32>();
                     p.turns.push(Turn::GameEnd(GameEnd::RemainingTiles(tiles)))
@@ -463,16 +876,57 @@ impl Game {
         Ok(())
     }
 
+    /// Put the whole rack of the player whose turn it is back in the bag, draw a new one and pass the
+    /// turn on. With the bag empty, the player draws back the same tiles.
     pub fn exchange_tiles(&mut self) {
+        self.previous = Some(Box::new(self.snapshot()));
+        self.actions.push(Action::ExchangeTiles);
         let player = &mut self.players[self.whose_turn];
         self.tile_bag.extend_from_slice(&player.tiles);
-        self.tile_bag.shuffle(&mut rand::thread_rng());
+        self.tile_bag.shuffle(&mut self.rng);
         player.turns.push(Turn::TilesExchanged);
         player.tiles.clear();
         player.refill_tiles_from(&mut self.tile_bag);
         self.advance_turn();
     }
 
+    /// Name of the player whose move or exchange can currently be taken back
+    pub fn last_turn_player(&self) -> Option<&str> {
+        self.previous
+            .as_ref()
+            .map(|g| g.players[g.whose_turn].name())
+    }
+
+    /// Restore the game to how it was before the most recent move or exchange: the board,
+    /// the player's tiles, the tiles they drew back into the bag, and whose turn it is.
+    /// Only one turn can be taken back. Returns false if there is nothing to take back.
+    pub fn undo_last_turn(&mut self) -> bool {
+        match self.previous.take() {
+            Some(previous) => {
+                *self = *previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// A copy of this game without its own history
+    fn snapshot(&self) -> Game {
+        Game {
+            board: self.board,
+            tile_bag: self.tile_bag.clone(),
+            players: self.players.clone(),
+            whose_turn: self.whose_turn,
+            finished: self.finished,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            joined_players: self.joined_players.clone(),
+            shuffle_players: self.shuffle_players,
+            actions: self.actions.clone(),
+            previous: None,
+        }
+    }
+
     fn index_of_player(&self, name: &str) -> Option<usize> {
         self.players
             .iter()
@@ -481,27 +935,37 @@ impl Game {
             .map(|(i, _)| i)
     }
 
+    /// Pass the turn to the next player who hasn't forfeited
     fn advance_turn(&mut self) {
-        self.whose_turn += 1;
-        self.whose_turn %= self.players.len();
+        loop {
+            self.whose_turn += 1;
+            self.whose_turn %= self.players.len();
+            if !self.players[self.whose_turn].forfeited {
+                break;
+            }
+        }
     }
 
     // fn player(&self, name: &str) -> Option<&Player> {
     //     self.index_of_player(name).map(|i| &self.players[i])
     // }
 
+    /// Whether it's the named player's turn
     pub fn is_players_turn(&self, name: &str) -> bool {
-        self.index_of_player(name).unwrap() == self.whose_turn
+        self.index_of_player(name) == Some(self.whose_turn)
     }
 
+    /// Whether there are enough players to start
     pub fn ready_to_play(&self) -> bool {
         self.players.len() >= 2
     }
 
+    /// Whether the named player is in the game, even if they've forfeited
     pub fn has_player(&self, name: &str) -> bool {
         self.index_of_player(name).is_some()
     }
 
+    /// Add a player before the game has started. Names should be unique, see [`Game::has_player`].
     pub fn add_player<T: Into<String>>(&mut self, name: T) {
         self.players.push(Player {
             name: name.into(),
@@ -509,13 +973,93 @@ impl Game {
         })
     }
 
+    /// Remove a player before the game has started. Returns false if there was no such player.
+    pub fn remove_player(&mut self, name: &str) -> bool {
+        let Some(i) = self.index_of_player(name) else {
+            return false;
+        };
+        self.players.remove(i);
+        true
+    }
+
+    /// Sort players into the given order before the game has started.
+    /// Players not named in `order` keep their relative order after the named ones.
+    pub fn reorder_players(&mut self, order: &[String]) {
+        self.players.sort_by_key(|p| {
+            order
+                .iter()
+                .position(|n| *n == p.name)
+                .unwrap_or(order.len())
+        });
+    }
+
+    /// Take a player out of a running game. Their tiles are returned to the bag and they are skipped
+    /// from now on. If only one player is left, the game is over.
+    /// Returns false if there is no such player still playing.
+    pub fn forfeit(&mut self, name: &str) -> bool {
+        let Some(i) = self.index_of_player(name) else {
+            return false;
+        };
+        if self.finished || self.players[i].forfeited {
+            return false;
+        }
+
+        // Forfeits can't be taken back, and neither can anything before them
+        self.previous = None;
+        self.actions.push(Action::Forfeit(name.to_owned()));
+        let player = &mut self.players[i];
+        self.tile_bag.append(&mut player.tiles);
+        self.tile_bag.shuffle(&mut self.rng);
+        player.forfeited = true;
+        player.turns.push(Turn::Forfeited);
+
+        if self.players.iter().filter(|p| !p.forfeited).count() <= 1 {
+            self.finished = true;
+        } else if i == self.whose_turn {
+            self.advance_turn();
+        }
+        true
+    }
+
+    /// The tiles played so far
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Every player in turn order, including those who have forfeited
     pub fn players(&self) -> &[Player] {
         &self.players
     }
 
-    // fn current_player(&self) -> &Player {
-    //     &self.players[self.whose_turn]
-    // }
+    /// Empty every rack but `name`'s, for showing the game to them. Nobody's rack is left for
+    /// spectators. What was left on each rack at the end of the game is still in the players' turns.
+    pub fn hide_racks(&mut self, name: Option<&str>) {
+        for p in &mut self.players {
+            if Some(p.name.as_str()) != name {
+                p.tiles.clear();
+            }
+        }
+    }
+
+    /// Whether the game is over, because someone went out or only one player is left
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// How many tiles are left to draw
+    pub fn tiles_in_bag(&self) -> usize {
+        self.tile_bag.len()
+    }
+
+    /// The player whose turn it is
+    pub fn current_player(&self) -> &Player {
+        &self.players[self.whose_turn]
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_finished(&mut self) {
+        self.finished = true;
+    }
 
     #[cfg(test)]
     fn current_player_mut(&mut self) -> &mut Player {
@@ -523,16 +1067,171 @@ impl Game {
     }
 
     fn game_finished_by_tiles(&self) -> bool {
-        self.tile_bag.is_empty() && self.players.iter().any(|p| p.tiles.is_empty())
+        self.tile_bag.is_empty()
+            && self
+                .players
+                .iter()
+                .any(|p| !p.forfeited && p.tiles.is_empty())
     }
 }
 
+fn serialize_len<S: serde::Serializer>(tiles: &[Tile], s: S) -> Result<S::Ok, S::Error> {
+    tiles.len().serialize(s)
+}
+
+fn serialize_last_turn_player<S: serde::Serializer>(
+    previous: &Option<Box<Game>>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    previous
+        .as_ref()
+        .map(|g| &g.players[g.whose_turn].name)
+        .serialize(s)
+}
+
 impl Display for Tile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.as_char().to_ascii_uppercase())
     }
 }
 
+/// Uppercase for a letter, lowercase for a blank
+impl Display for BoardTile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardTile::Blank(l) => write!(f, "{}", l.as_char()),
+            t => write!(f, "{}", t.as_letter().as_char().to_ascii_uppercase()),
+        }
+    }
+}
+
+/// Standard coordinate notation: rows 1 to 15 and columns A to O, with the row first for
+/// horizontal moves ("8H CAT") and the column first for vertical ones ("H8 CAT").
+/// Squares between the tiles that the move doesn't cover are shown as `.`, and blanks are lowercase.
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let m = self.sorted();
+        let Some(&((x0, y0), _)) = m.tiles.first() else {
+            return write!(f, "-");
+        };
+        let coordinates = |x: usize, y: usize| (x + 1, char::from(b'A' + y as u8));
+        if !m.is_straight_line() {
+            // Not a real move, but it may still need printing
+            for ((x, y), t) in &m.tiles {
+                let (row, column) = coordinates(*x, *y);
+                write!(f, "{row}{column}:{} ", t.as_letter().as_char())?;
+            }
+            return Ok(());
+        }
+
+        let (row, column) = coordinates(x0, y0);
+        let horizontal = m.is_horizontal();
+        if horizontal {
+            write!(f, "{row}{column} ")?;
+        } else {
+            write!(f, "{column}{row} ")?;
+        }
+
+        let mut next = (x0, y0);
+        for ((x, y), t) in &m.tiles {
+            while next < (*x, *y) {
+                write!(f, ".")?;
+                next = if horizontal {
+                    (next.0, next.1 + 1)
+                } else {
+                    (next.0 + 1, next.1)
+                };
+            }
+            write!(f, "{t}")?;
+            next = if horizontal { (*x, y + 1) } else { (x + 1, *y) };
+        }
+        Ok(())
+    }
+}
+
+/// A grid of 15 lines of 15 squares, with uppercase letters for tiles and lowercase for blanks.
+/// Empty squares show their premium: `=` triple word, `-` double word, `"` triple letter and
+/// `'` double letter. The alternate form, `{:#}`, adds row numbers and column letters.
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use solve::Modifier::*;
+
+        if f.alternate() {
+            writeln!(f, "   ABCDEFGHIJKLMNO")?;
+        }
+        for x in 0..15 {
+            if f.alternate() {
+                write!(f, "{:>2} ", x + 1)?;
+            }
+            for y in 0..15 {
+                match (self[x][y], solve::MODIFIERS.get(&(x, y))) {
+                    (Some(t), _) => write!(f, "{t}")?,
+                    (None, Some(TripleWord)) => write!(f, "=")?,
+                    (None, Some(DoubleWord)) => write!(f, "-")?,
+                    (None, Some(TripleLetter)) => write!(f, "\"")?,
+                    (None, Some(DoubleLetter)) => write!(f, "'")?,
+                    (None, None) => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads either form printed by [`Board`]'s `Display`. Any character other than a letter is
+/// an empty square, and short lines are padded with empty squares. Blank lines are ignored.
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| !l.is_empty() && l.trim() != "ABCDEFGHIJKLMNO")
+            .map(|l| {
+                // Row numbers, in the alternate form
+                let numbered = l.trim_start();
+                let rest = numbered.trim_start_matches(|c: char| c.is_ascii_digit());
+                if rest.len() < numbered.len() {
+                    rest.strip_prefix(' ').unwrap_or(rest)
+                } else {
+                    l
+                }
+            })
+            .collect();
+        if lines.len() != 15 {
+            return Err(format!("Expected 15 lines, got {}", lines.len()));
+        }
+
+        let mut board = Board::new();
+        for (x, line) in lines.iter().enumerate() {
+            let squares: Vec<char> = line.chars().collect();
+            if squares.len() > 15 {
+                return Err(format!(
+                    "Line {} has {} squares, not 15",
+                    x + 1,
+                    squares.len()
+                ));
+            }
+            for (y, c) in squares.into_iter().enumerate() {
+                board[x][y] = BoardTile::from_char(c);
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Reads the notation printed by [`Move`]'s `Display`, placing every letter and skipping squares
+/// marked `.`. Use [`Move::parse`] to skip the squares already filled on a board instead.
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Move::parse(&Board::new(), s)
+    }
+}
+
 impl Index<usize> for Board {
     type Output = [Option<BoardTile>; 15];
 
@@ -556,6 +1255,7 @@ impl Default for Game {
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn known_game_constants() {
@@ -568,30 +1268,87 @@ mod test {
         assert_eq!(Board::default()[0].len(), 15);
     }
 
-    fn play_move<T: Into<BoardTile> + Copy>(
-        g: &mut Game,
-        m: &[(usize, usize, T)],
-    ) -> Result<(), InvalidMove> {
+    /// Play a move in notation like "8H CAT", giving the current player the tiles for it
+    fn play_move(g: &mut Game, notation: &str) -> Result<(), InvalidMove> {
+        let m: Move = notation.parse().unwrap();
         let p = g.current_player_mut();
-        let mut tiles = Vec::new();
-        for (x, y, t) in m.iter().copied() {
-            let t = t.into();
-            tiles.push(((x, y), t));
-            p.tiles.push(t.as_tile());
-        }
-        g.play_move(&Move::new(tiles))
+        p.tiles.extend(m.tiles.iter().map(|(_, t)| t.as_tile()));
+        g.play_move(&m)
     }
 
     fn game(n_players: usize) -> Game {
         const PLAYERS: &[&str] = &["Alice", "Bob", "Charlie", "Daniel", "Elizabeth", "Frank"];
-        let mut g = Game::new();
+        let mut g = Game::with_seed(0);
         for p in PLAYERS.iter().take(n_players) {
             g.add_player(*p);
         }
-        g.start_game();
+        g.start_game(false);
         g
     }
 
+    #[test]
+    fn move_notation() {
+        use BoardTile::*;
+
+        let m = Move::new(vec![((7, 9), T), ((7, 7), C), ((7, 8), Blank(Letter::A))]);
+        assert_eq!(m.to_string(), "8H CaT");
+        let m = Move::new(vec![((2, 0), Q), ((4, 0), I)]);
+        assert_eq!(m.to_string(), "A3 Q.I");
+
+        let board = Board::new();
+        let parse = |s| Move::parse(&board, s);
+        assert_eq!(parse("8H CaT").unwrap().to_string(), "8H CaT");
+        assert_eq!(parse("H8 CAT").unwrap().to_string(), "H8 CAT");
+        assert_eq!(parse("h8 across CAT"), parse("8H CAT"));
+        parse("P8 CAT").unwrap_err();
+        parse("8N CAT").unwrap_err();
+        parse("H8 sideways CAT").unwrap_err();
+        parse("8H C4T").unwrap_err();
+
+        // Letters on the board are skipped, typed or not
+        let board = board.with_move_applied(&Move::new(vec![((7, 7), C)]));
+        let parse = |s| Move::parse(&board, s);
+        let cats = parse("8H CATS").unwrap();
+        assert_eq!(Ok(&cats), parse("8H .ATS").as_ref());
+        assert_eq!(cats.to_string(), "8I ATS");
+        parse("8H DOG").unwrap_err();
+    }
+
+    #[test]
+    fn board_display() {
+        let board = Board::new().with_move_applied(&"8G OaT".parse().unwrap());
+        let grid = board.to_string();
+        assert_eq!(grid.lines().next(), Some("=..'...=...'..="));
+        assert_eq!(grid.lines().nth(7), Some("=..'..OaT..'..="));
+        assert_eq!(grid.parse(), Ok(board));
+        let numbered = format!("{board:#}");
+        assert_eq!(numbered.lines().nth(8), Some(" 8 =..'..OaT..'..="));
+        assert_eq!(numbered.parse(), Ok(board));
+
+        let m: Move = "A3 Q.I".parse().unwrap();
+        assert_eq!(m.to_string().parse(), Ok(m));
+    }
+
+    #[test]
+    fn board_grid() {
+        let mut grid = vec!["..............."; 15];
+        grid[7] = ".......CaT......";
+        grid.join("\n").parse::<Board>().unwrap_err();
+        grid[7] = ".......CaT.....";
+        let board: Board = grid.join("\n").parse().unwrap();
+        assert_eq!(board[7][7], Some(BoardTile::C));
+        assert_eq!(board[7][8], Some(BoardTile::Blank(Letter::A)));
+        assert_eq!(board[7][10], None);
+        grid.pop();
+        grid.join("\n").parse::<Board>().unwrap_err();
+
+        assert_eq!(
+            Tile::parse_rack("qI?"),
+            Ok(vec![Tile::Q, Tile::I, Tile::Blank])
+        );
+        Tile::parse_rack("QI!").unwrap_err();
+    }
+
     #[test]
     fn extra_50_points_test() {
         fn value_of_letters(s: &str) -> u32 {
@@ -642,7 +1399,7 @@ mod test {
         g.players[0].tiles = vec![]; // test::play_move adds necessary tiles on demand
         g.players[1].tiles = vec![Z, Z, Z, Z, Z]; // 5 * 10 points
 
-        play_move(&mut g, &[(7, 7, F), (7, 8, I), (7, 9, N)]).unwrap();
+        play_move(&mut g, "8H FIN").unwrap();
         assert!(g.finished);
         assert_eq!(g.players[0].turns.len(), 2);
 
@@ -653,22 +1410,282 @@ mod test {
         let Turn::GameEnd(GameEnd::RemainingTiles(ts)) = g.players[1].turns[0].clone() else {
             unreachable!()
         };
-        assert_eq!(ts, &[Z, Z, Z, Z, Z])
+        assert_eq!(ts, &[Z, Z, Z, Z, Z]);
+
+        // FIN on the center double word, plus Bob's 50 points of Zs
+        assert_eq!(g.players[0].score(), 6 * 2 + 50);
+        assert_eq!(g.players[1].score(), -50);
     }
 
     #[test]
     fn two_player_game_test() {
-        use BoardTile::*;
-
         let mut g = game(2);
 
         // Doesn't cross the center
-        play_move(&mut g, &[(0, 0, T), (0, 1, O), (0, 2, P)]).unwrap_err();
+        play_move(&mut g, "1A TOP").unwrap_err();
 
-        play_move(&mut g, &[(7, 6, O), (7, 7, A), (7, 8, T)]).unwrap();
-        play_move(&mut g, &[(5, 8, N), (6, 8, U)]).unwrap();
+        play_move(&mut g, "8G OAT").unwrap();
+        play_move(&mut g, "I6 NU").unwrap();
 
         // Is disconnected from existing tiles
-        play_move(&mut g, &[(0, 0, T), (0, 1, O), (0, 2, P)]).unwrap_err();
+        play_move(&mut g, "1A TOP").unwrap_err();
+    }
+
+    #[test]
+    fn invalid_move_diagnostics() {
+        let mut g = game(2);
+        let im = play_move(&mut g, "8F C.A.T").unwrap_err();
+        assert_eq!(im.gaps, [(7, 6), (7, 8)]);
+        assert_eq!(im.positions, im.gaps);
+
+        play_move(&mut g, "8G OAT").unwrap();
+
+        // X only reads across, as OATX, which isn't a word
+        let im = play_move(&mut g, "8J X").unwrap_err();
+        assert_eq!(im.axis, Some(Axis::Horizontal));
+        assert_eq!(im.invalid_words.len(), 1);
+        assert_eq!(im.invalid_words[0].word, "OATX");
+
+        // QX down through the O, and QQ across
+        let im = play_move(&mut g, "7F QQ").unwrap_err();
+        let words: Vec<_> = im.invalid_words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(words, ["QQ", "QO"]);
+        assert_eq!(im.explanation, "'QQ' and 'QO' are not words");
+        assert_eq!(im.invalid_words[1].positions, [(6, 6), (7, 6)]);
+
+        // Off the edge of the board, which a client could send but can't be typed
+        let off = Move::new(vec![((7, 9), BoardTile::A), ((7, 15), BoardTile::T)]);
+        g.current_player_mut().tiles.extend([Tile::A, Tile::T]);
+        let im = g.play_move(&off).unwrap_err();
+        assert_eq!(im.explanation, "Move is off the board (impossible)");
+    }
+
+    #[test]
+    fn undo_test() {
+        let mut g = game(2);
+        assert!(!g.undo_last_turn());
+
+        let before = g.snapshot();
+        play_move(&mut g, "8G OAT").unwrap();
+        assert_eq!(g.last_turn_player(), Some("Alice"));
+        assert!(g.board[7][7].is_some());
+
+        assert!(g.undo_last_turn());
+        assert!(g.board.is_empty());
+        assert_eq!(g.tile_bag, before.tile_bag);
+        assert!(g.is_players_turn("Alice"));
+        assert!(g.players[0].turns.is_empty());
+        assert!(!g.undo_last_turn());
+    }
+
+    #[test]
+    fn replay_test() {
+        let mut g = Game::with_seed(1234);
+        for p in ["Alice", "Bob", "Charlie"] {
+            g.add_player(p);
+        }
+        g.start_game(true);
+        g.exchange_tiles();
+        g.forfeit("Charlie");
+        g.exchange_tiles();
+
+        let replayed = Game::replay(&g.record()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&g).unwrap()
+        );
+
+        // Tiles that didn't come from the seeded draws can't be replayed
+        let rack = &mut g.current_player_mut().tiles;
+        rack[0] = Tile::H;
+        rack[1] = Tile::I;
+        g.play_move(&"8H HI".parse().unwrap()).unwrap();
+        assert_eq!(Game::replay(&g.record()).unwrap_err().action, Some(3));
+    }
+
+    #[test]
+    fn replay_rejects_bad_records() {
+        let record = |players: &[&str], actions| GameRecord {
+            seed: 0,
+            players: players.iter().map(|p| p.to_string()).collect(),
+            shuffle_players: false,
+            actions,
+        };
+        let error = |r| Game::replay(&r).unwrap_err();
+        assert_eq!(error(record(&[], vec![])).action, None);
+        assert_eq!(error(record(&["Alice"], vec![])).action, None);
+        assert_eq!(error(record(&["Alice", "Alice"], vec![])).action, None);
+
+        let over = vec![Action::Forfeit("Alice".into()), Action::ExchangeTiles];
+        assert_eq!(error(record(&["Alice", "Bob"], over)).action, Some(1));
+        let names: Vec<_> = (0..20).map(|i| format!("Player {i}")).collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        Game::replay(&record(&names, vec![Action::ExchangeTiles])).unwrap();
+    }
+
+    #[test]
+    fn forfeit_test() {
+        let mut g = game(3);
+        assert!(g.is_players_turn("Alice"));
+
+        assert!(g.forfeit("Alice"));
+        assert!(!g.forfeit("Alice"));
+        assert!(g.players[0].tiles.is_empty());
+        assert_eq!(g.tile_bag.len(), 100 - 14);
+        assert!(g.is_players_turn("Bob"));
+
+        g.exchange_tiles();
+        g.exchange_tiles();
+        assert!(g.is_players_turn("Bob"));
+
+        assert!(g.forfeit("Charlie"));
+        assert!(g.finished);
+    }
+
+    /// A two player game `turns` moves in, each player playing a random valid move or exchanging
+    fn random_game(seed: u64, turns: usize) -> Game {
+        let mut g = Game::with_seed(seed);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        for _ in 0..turns {
+            if g.finished {
+                break;
+            }
+            let moves = solve::generate_moves(&g.board, &g.current_player().tiles);
+            match moves.choose(&mut rng) {
+                Some((m, _)) => g.play_move(m).unwrap(),
+                None => g.exchange_tiles(),
+            }
+        }
+        g
+    }
+
+    fn tile_count(g: &Game) -> usize {
+        let on_board = g.board.0.iter().flatten().flatten().count();
+        let on_racks: usize = g.players.iter().map(|p| p.tiles.len()).sum();
+        on_board + on_racks + g.tile_bag.len()
+    }
+
+    /// A move the generator found for `rack`, then changed a little: a tile replaced by another
+    /// letter or by a blank, a tile taken away, or the whole move shifted down a square. Unlike
+    /// moves made up from scratch, many of these are still accepted.
+    fn near_move(
+        board: &Board,
+        rack: &[Tile],
+        pick: prop::sample::Index,
+        mutation: u8,
+        which: prop::sample::Index,
+        letter: u8,
+    ) -> Option<Move> {
+        let moves = solve::generate_moves(board, rack);
+        if moves.is_empty() {
+            return None;
+        }
+        let mut tiles = moves[pick.index(moves.len())].0.tiles.clone();
+        let i = which.index(tiles.len());
+        match mutation {
+            1 => tiles[i].1 = Tile::from_ascii(b'a' + letter).into(),
+            2 => tiles[i].1 = BoardTile::Blank(Letter::from_ascii(b'a' + letter)),
+            3 if tiles.len() > 1 => {
+                tiles.remove(i);
+            }
+            4 => tiles.iter_mut().for_each(|((x, _), _)| *x += 1),
+            _ => {}
+        }
+        Some(Move::new(tiles))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn accepted_moves_form_words(
+            seed: u64,
+            turns in 0..10usize,
+            rack in prop::collection::vec(0..26u8, 7),
+            pick: prop::sample::Index,
+            mutation in 0..5u8,
+            which: prop::sample::Index,
+            letter in 0..26u8,
+        ) {
+            let g = random_game(seed, turns);
+            let rack: Vec<_> = rack.into_iter().map(|i| Tile::from_ascii(b'a' + i)).collect();
+            let Some(m) = near_move(&g.board, &rack, pick, mutation, which, letter) else {
+                return Ok(());
+            };
+            let mut board = g.board;
+            match board.play_move(&m) {
+                Ok(played) => {
+                    for (word, _) in played.word_values() {
+                        prop_assert!(solve::is_word(word), "{word} accepted in {m}");
+                    }
+                    prop_assert_eq!(played.value(), solve::score_play(&g.board, &m));
+                }
+                Err(_) => {
+                    prop_assert!(mutation != 0, "{m} was generated but refused");
+                    prop_assert_eq!(board, g.board);
+                }
+            }
+        }
+
+        #[test]
+        fn generated_moves_are_accepted(seed: u64, turns in 0..10usize) {
+            let g = random_game(seed, turns);
+            for (m, score) in solve::generate_moves(&g.board, &g.current_player().tiles) {
+                let mut board = g.board;
+                let played = board.play_move(&m);
+                prop_assert_eq!(played.map(|p| p.value()).ok(), Some(score), "{}", m);
+            }
+        }
+
+        #[test]
+        fn score_ignores_tile_order(seed: u64, turns in 0..10usize, shuffle: u64) {
+            let g = random_game(seed, turns);
+            let mut rng = ChaCha8Rng::seed_from_u64(shuffle);
+            let moves = solve::generate_moves(&g.board, &g.current_player().tiles);
+            for (m, score) in moves.choose_multiple(&mut rng, 20) {
+                let mut tiles = m.tiles.clone();
+                tiles.shuffle(&mut rng);
+                let unsorted = Move { tiles: tiles.clone(), sorted: false };
+                prop_assert_eq!(solve::score_play(&g.board, &unsorted), *score);
+
+                let mut board = g.board;
+                let played = board.play_move(&Move::new(tiles)).unwrap();
+                prop_assert_eq!(played.value(), *score);
+                prop_assert_eq!(board, g.board.with_move_applied(m));
+            }
+        }
+
+        #[test]
+        fn tiles_are_conserved(seed: u64, actions in prop::collection::vec(0..6u8, 0..40)) {
+            let mut g = random_game(seed, 0);
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            prop_assert_eq!(tile_count(&g), 100);
+            for action in actions {
+                if g.finished {
+                    break;
+                }
+                match action {
+                    0 => g.exchange_tiles(),
+                    1 => {
+                        g.undo_last_turn();
+                    }
+                    2 => {
+                        let name = g.current_player().name.clone();
+                        g.forfeit(&name);
+                    }
+                    _ => {
+                        let moves = solve::generate_moves(&g.board, &g.current_player().tiles);
+                        match moves.choose(&mut rng) {
+                            Some((m, _)) => g.play_move(m).unwrap(),
+                            None => g.exchange_tiles(),
+                        }
+                    }
+                }
+                prop_assert_eq!(tile_count(&g), 100);
+            }
+        }
     }
 }
@@ -0,0 +1,169 @@
+//! Post-game analysis: replay a game and compare every move played with the best one available.
+
+use serde::Serialize;
+
+use super::{solve, Action, Game, GameRecord, Move, ReplayError};
+
+/// How many missed bingos to list for a single move
+const MAX_MISSED_BINGOS: usize = 5;
+
+/// One played move next to the best move the player could have made instead.
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveAnalysis {
+    /// Who played the move
+    pub player: String,
+    /// The move that was played
+    pub played: Move,
+    /// What the played move scored
+    pub score: u32,
+    /// The highest scoring move with the same rack
+    pub best: Move,
+    /// What the best move would have scored
+    pub best_score: u32,
+    /// Moves using all seven tiles that the player didn't find, highest scoring first
+    pub missed_bingos: Vec<(Move, u32)>,
+}
+
+impl MoveAnalysis {
+    /// Points left on the board by not playing the best move
+    pub fn difference(&self) -> u32 {
+        self.best_score - self.score
+    }
+}
+
+/// How close one player's moves came to the best available.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerAccuracy {
+    /// The player's name
+    pub name: String,
+    /// How many moves they played, not counting exchanges
+    pub moves: usize,
+    /// Moves that scored as much as the best move
+    pub best_moves: usize,
+    /// Points scored by their moves
+    pub points: u32,
+    /// Points the best move would have scored each turn, added up
+    pub best_points: u32,
+    /// Turns on which a bingo was available but not played
+    pub missed_bingos: usize,
+    /// Points scored as a fraction of the points available, 1 for a perfect game
+    pub accuracy: f64,
+}
+
+/// Every move of a game compared with the best available, and how each player did overall.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameAnalysis {
+    /// Each move played, in order
+    pub moves: Vec<MoveAnalysis>,
+    /// Each player, in turn order
+    pub players: Vec<PlayerAccuracy>,
+}
+
+impl Game {
+    /// Replay a record, and before every move find the best move by score with the rack the player had.
+    /// Exchanges and forfeits aren't analyzed.
+    pub fn analyze(record: &GameRecord) -> Result<GameAnalysis, ReplayError> {
+        let mut moves = vec![];
+        let game = Game::replay_with(record, |g, action| {
+            let Action::PlayMove(played) = action else {
+                return;
+            };
+            let player = g.current_player();
+            let mut options = solve::generate_moves(&g.board, &player.tiles);
+            options.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+
+            // An invalid move fails the replay right after this, so its score doesn't matter
+            let mut board = g.board;
+            let score = board.play_move(played).map_or(0, |pm| pm.value());
+            let (best, best_score) = match options.first() {
+                Some((m, s)) if *s > score => (m.clone(), *s),
+                _ => (played.clone(), score),
+            };
+            let missed_bingos = if played.tiles.len() == 7 {
+                vec![]
+            } else {
+                options
+                    .into_iter()
+                    .filter(|(m, _)| m.tiles.len() == 7)
+                    .take(MAX_MISSED_BINGOS)
+                    .collect()
+            };
+
+            moves.push(MoveAnalysis {
+                player: player.name.clone(),
+                played: played.clone(),
+                score,
+                best,
+                best_score,
+                missed_bingos,
+            });
+        })?;
+
+        let players = game
+            .players
+            .iter()
+            .map(|p| {
+                let theirs: Vec<_> = moves.iter().filter(|m| m.player == p.name).collect();
+                let points = theirs.iter().map(|m| m.score).sum();
+                let best_points = theirs.iter().map(|m| m.best_score).sum();
+                PlayerAccuracy {
+                    name: p.name.clone(),
+                    moves: theirs.len(),
+                    best_moves: theirs.iter().filter(|m| m.difference() == 0).count(),
+                    points,
+                    best_points,
+                    missed_bingos: theirs
+                        .iter()
+                        .filter(|m| !m.missed_bingos.is_empty())
+                        .count(),
+                    accuracy: if best_points == 0 {
+                        1.0
+                    } else {
+                        points as f64 / best_points as f64
+                    },
+                }
+            })
+            .collect();
+
+        Ok(GameAnalysis { moves, players })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn analyze_game() {
+        let mut g = Game::with_seed(3);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+
+        // Alice plays her best move, Bob exchanges, then Alice plays her worst
+        let mut moves = solve::generate_moves(&g.board, &g.current_player().tiles);
+        moves.sort_by_key(|(_, s)| *s);
+        g.play_move(&moves.last().unwrap().0).unwrap();
+        g.exchange_tiles();
+        let mut moves = solve::generate_moves(&g.board, &g.current_player().tiles);
+        moves.sort_by_key(|(_, s)| *s);
+        let (worst, worst_score) = moves[0].clone();
+        let best_score = moves.last().unwrap().1;
+        g.play_move(&worst).unwrap();
+
+        let analysis = Game::analyze(&g.record()).unwrap();
+        assert_eq!(analysis.moves.len(), 2);
+        assert_eq!(analysis.moves[0].difference(), 0);
+        assert_eq!(analysis.moves[0].best, analysis.moves[0].played);
+        assert_eq!(analysis.moves[1].played, worst);
+        assert_eq!(analysis.moves[1].score, worst_score);
+        assert_eq!(analysis.moves[1].best_score, best_score);
+
+        let alice = &analysis.players[0];
+        assert_eq!((alice.moves, alice.best_moves), (2, 1));
+        assert_eq!(alice.points as i32, g.players[0].score());
+        assert!(alice.accuracy < 1.0);
+        let bob = &analysis.players[1];
+        assert_eq!((bob.moves, bob.accuracy), (0, 1.0));
+    }
+}
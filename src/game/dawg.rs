@@ -0,0 +1,403 @@
+//! A directed acyclic word graph: a trie with identical suffixes merged, so that
+//! the whole word list fits in a couple of megabytes and can be walked letter by letter.
+//!
+//! The graph is stored as a flat array of little-endian `u32` edges. Each node is a run of
+//! consecutive edges, and each edge packs:
+//! - bits 0..5: the letter, 0 for 'a' through 25 for 'z'
+//! - bit 5: set on the last edge of a node
+//! - bit 6: set if the node the edge leads to ends a word
+//! - bits 7..32: index of the first edge of the node the edge leads to, or 0 if it has none
+//!
+//! Edge 0 is unused so that 0 can mean "no children", and the root node starts at edge 1.
+//!
+//! This file is also compiled into `build.rs`, which builds the graph ahead of time,
+//! so it must only depend on `std`.
+
+use std::{borrow::Cow, collections::HashMap};
+
+const LETTER_MASK: u32 = 0b11111;
+const LAST_EDGE: u32 = 1 << 5;
+const TERMINAL: u32 = 1 << 6;
+const TARGET_SHIFT: u32 = 7;
+
+/// A word list as a graph, either borrowed from bytes built ahead of time or built from words.
+pub struct Dawg {
+    bytes: Cow<'static, [u8]>,
+}
+
+/// A position in the graph, reached by following the letters of some prefix from the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Node {
+    index: u32,
+    terminal: bool,
+}
+
+impl Node {
+    /// Whether the prefix that leads to this node is a word
+    pub fn is_terminal(self) -> bool {
+        self.terminal
+    }
+}
+
+impl Dawg {
+    /// A graph stored in the format described above, such as the one written by `build.rs`
+    pub fn from_bytes(bytes: &'static [u8]) -> Dawg {
+        assert!(
+            bytes.len().is_multiple_of(4),
+            "DAWG is not a whole number of edges"
+        );
+        Dawg {
+            bytes: Cow::Borrowed(bytes),
+        }
+    }
+
+    fn edge(&self, i: u32) -> u32 {
+        let i = i as usize * 4;
+        u32::from_le_bytes(self.bytes[i..i + 4].try_into().unwrap())
+    }
+
+    /// The node for the empty prefix, where every word starts
+    pub fn root(&self) -> Node {
+        let index = if self.bytes.len() > 4 { 1 } else { 0 };
+        Node {
+            index,
+            terminal: false,
+        }
+    }
+
+    /// Every letter that can follow `node`, as a lowercase ascii byte, and the node it leads to
+    pub fn children(&self, node: Node) -> impl Iterator<Item = (u8, Node)> + '_ {
+        let mut i = node.index;
+        std::iter::from_fn(move || {
+            if i == 0 {
+                return None;
+            }
+            let edge = self.edge(i);
+            i = if edge & LAST_EDGE != 0 { 0 } else { i + 1 };
+            let child = Node {
+                index: edge >> TARGET_SHIFT,
+                terminal: edge & TERMINAL != 0,
+            };
+            Some((b'a' + (edge & LETTER_MASK) as u8, child))
+        })
+    }
+
+    /// The node `letter` leads to from `node`, if any word continues that way
+    pub fn child(&self, node: Node, letter: u8) -> Option<Node> {
+        self.children(node)
+            .find(|(l, _)| *l == letter)
+            .map(|(_, n)| n)
+    }
+
+    /// Follow `prefix` from the root. Returns `None` if no word starts with `prefix`.
+    pub fn lookup(&self, prefix: &str) -> Option<Node> {
+        prefix
+            .bytes()
+            .try_fold(self.root(), |node, letter| self.child(node, letter))
+    }
+
+    /// Whether `word`, in lowercase, is in the word list
+    pub fn contains(&self, word: &str) -> bool {
+        self.lookup(word).is_some_and(Node::is_terminal)
+    }
+}
+
+impl Dawg {
+    /// Every word of at least two letters that can be spelled with some of `letters`,
+    /// where `b'?'` is a blank that can be any letter
+    pub fn anagrams(&self, letters: &[u8]) -> Vec<String> {
+        fn walk(
+            dawg: &Dawg,
+            node: Node,
+            counts: &mut [u8; 27],
+            word: &mut String,
+            out: &mut Vec<String>,
+        ) {
+            if node.terminal && word.len() >= 2 {
+                out.push(word.clone());
+            }
+            for (letter, child) in dawg.children(node) {
+                let i = (letter - b'a') as usize;
+                for i in [i, 26] {
+                    if counts[i] == 0 {
+                        continue;
+                    }
+                    counts[i] -= 1;
+                    word.push(char::from(letter));
+                    walk(dawg, child, counts, word, out);
+                    word.pop();
+                    counts[i] += 1;
+                    // A word spelled with a real tile doesn't also need to be found with a blank
+                    break;
+                }
+            }
+        }
+
+        let mut counts = [0u8; 27];
+        for c in letters {
+            match c {
+                b'a'..=b'z' => counts[(c - b'a') as usize] += 1,
+                b'?' => counts[26] += 1,
+                _ => {}
+            }
+        }
+        let mut out = Vec::new();
+        walk(self, self.root(), &mut counts, &mut String::new(), &mut out);
+        out
+    }
+
+    /// Up to `limit` words matching `pattern` in alphabetical order, where `b'?'` matches any one
+    /// letter and `b'*'` matches any number of letters. Patterns longer than 63 bytes match nothing.
+    pub fn matching(&self, pattern: &[u8], limit: usize) -> Vec<String> {
+        // Rather than trying every way the stars could split a word, the walk keeps the set of
+        // positions in the pattern that the letters so far could have reached, as a bitmask.
+        // That way every path through the graph is walked at most once.
+        fn skip_stars(pattern: &[u8], mut states: u64) -> u64 {
+            for (i, &p) in pattern.iter().enumerate() {
+                if p == b'*' && states & 1 << i != 0 {
+                    states |= 1 << (i + 1);
+                }
+            }
+            states
+        }
+
+        fn step(pattern: &[u8], states: u64, letter: u8) -> u64 {
+            let mut next = 0;
+            for (i, &p) in pattern.iter().enumerate() {
+                if states & 1 << i == 0 {
+                    continue;
+                }
+                if p == b'*' {
+                    next |= 1 << i;
+                } else if p == b'?' || p == letter {
+                    next |= 1 << (i + 1);
+                }
+            }
+            skip_stars(pattern, next)
+        }
+
+        fn walk(
+            dawg: &Dawg,
+            node: Node,
+            pattern: &[u8],
+            states: u64,
+            word: &mut String,
+            out: &mut Vec<String>,
+            limit: usize,
+        ) {
+            if node.terminal && states & 1 << pattern.len() != 0 {
+                out.push(word.clone());
+            }
+            for (letter, child) in dawg.children(node) {
+                if out.len() >= limit {
+                    return;
+                }
+                let next = step(pattern, states, letter);
+                if next != 0 {
+                    word.push(char::from(letter));
+                    walk(dawg, child, pattern, next, word, out, limit);
+                    word.pop();
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        if pattern.len() < 64 {
+            let states = skip_stars(pattern, 1);
+            let mut word = String::new();
+            walk(
+                self,
+                self.root(),
+                pattern,
+                states,
+                &mut word,
+                &mut out,
+                limit,
+            );
+        }
+        out
+    }
+}
+
+// Building is done by build.rs, and here only by tests
+#[allow(dead_code)]
+impl Dawg {
+    /// Build a graph from a sorted list of lowercase ascii words.
+    /// Uses the incremental algorithm from Daciuk et al, "Incremental Construction of Minimal Acyclic Finite-State Automata".
+    pub fn from_words<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Dawg {
+        Dawg {
+            bytes: Cow::Owned(Builder::default().build(words)),
+        }
+    }
+
+    /// The graph in the format read by [`Dawg::from_bytes`]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[derive(Default)]
+struct BuildNode {
+    terminal: bool,
+    edges: Vec<(u8, usize)>,
+}
+
+#[derive(Default)]
+struct Builder {
+    nodes: Vec<BuildNode>,
+    /// Node slots freed by merging, to be reused
+    free: Vec<usize>,
+    /// Nodes that are already minimal, by their contents
+    register: HashMap<(bool, Vec<(u8, usize)>), usize>,
+    /// The path of the last word added that hasn't been minimized yet, as (parent, letter, child)
+    unchecked: Vec<(usize, u8, usize)>,
+}
+
+impl Builder {
+    fn build<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Vec<u8> {
+        self.nodes.push(BuildNode::default());
+        let mut previous = String::new();
+
+        for word in words {
+            let word = word.as_ref();
+            assert!(
+                word.bytes().all(|c| c.is_ascii_lowercase()),
+                "{word:?} is not lowercase ascii"
+            );
+            assert!(word > previous.as_str(), "words are not sorted at {word:?}");
+
+            let common = word
+                .bytes()
+                .zip(previous.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            self.minimize(common);
+
+            let mut node = self.unchecked.last().map_or(0, |(_, _, child)| *child);
+            for letter in word[common..].bytes() {
+                let child = self.new_node();
+                self.nodes[node].edges.push((letter - b'a', child));
+                self.unchecked.push((node, letter - b'a', child));
+                node = child;
+            }
+            self.nodes[node].terminal = true;
+            previous = word.to_owned();
+        }
+        self.minimize(0);
+
+        self.freeze()
+    }
+
+    fn new_node(&mut self) -> usize {
+        match self.free.pop() {
+            Some(i) => i,
+            None => {
+                self.nodes.push(BuildNode::default());
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn minimize(&mut self, down_to: usize) {
+        while self.unchecked.len() > down_to {
+            let (parent, letter, child) = self.unchecked.pop().unwrap();
+            let key = (self.nodes[child].terminal, self.nodes[child].edges.clone());
+            match self.register.get(&key) {
+                Some(&existing) => {
+                    *self.nodes[parent].edges.last_mut().unwrap() = (letter, existing);
+                    self.nodes[child] = BuildNode::default();
+                    self.free.push(child);
+                }
+                None => {
+                    self.register.insert(key, child);
+                }
+            }
+        }
+    }
+
+    fn freeze(self) -> Vec<u8> {
+        // Lay out nodes breadth first, giving each one the index of its first edge
+        let mut offsets = vec![0u32; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        visited[0] = true;
+        let mut order = vec![0];
+        let mut next_offset = 1;
+        let mut i = 0;
+        while i < order.len() {
+            let node = &self.nodes[order[i]];
+            if !node.edges.is_empty() {
+                offsets[order[i]] = next_offset;
+                next_offset += node.edges.len() as u32;
+            }
+            for &(_, child) in &node.edges {
+                if !visited[child] {
+                    visited[child] = true;
+                    order.push(child);
+                }
+            }
+            i += 1;
+        }
+        assert!(next_offset < 1 << (32 - TARGET_SHIFT), "DAWG is too large");
+
+        let mut edges = vec![0u32; next_offset as usize];
+        for &n in &order {
+            let node = &self.nodes[n];
+            for (j, &(letter, child)) in node.edges.iter().enumerate() {
+                let mut edge = letter as u32 | offsets[child] << TARGET_SHIFT;
+                if j == node.edges.len() - 1 {
+                    edge |= LAST_EDGE;
+                }
+                if self.nodes[child].terminal {
+                    edge |= TERMINAL;
+                }
+                edges[offsets[n] as usize + j] = edge;
+            }
+        }
+
+        edges.iter().flat_map(|e| e.to_le_bytes()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_lookup() {
+        let dawg = Dawg::from_words(["cat", "cats", "do", "dog", "dogs"]);
+        assert!(dawg.contains("cats"));
+        assert!(dawg.contains("do"));
+        assert!(!dawg.contains("ca"));
+        assert!(!dawg.contains("dogsx"));
+        assert!(dawg.lookup("ca").is_some());
+        assert!(dawg.lookup("x").is_none());
+
+        let letters: Vec<u8> = dawg.children(dawg.root()).map(|(l, _)| l).collect();
+        assert_eq!(letters, b"cd");
+
+        // "cat" and "dog" share the node that leads to "s": the unused edge 0,
+        // c and d from the root, a-t and o-g, then one s
+        assert_eq!(dawg.as_bytes().len() / 4, 1 + 2 + 2 + 2 + 1);
+    }
+
+    #[test]
+    fn search() {
+        let dawg = Dawg::from_words(["act", "at", "cat", "cats", "coat", "scat", "ta"]);
+        let mut anagrams = dawg.anagrams(b"tac");
+        anagrams.sort();
+        assert_eq!(anagrams, ["act", "at", "cat", "ta"]);
+        let mut anagrams = dawg.anagrams(b"ta?");
+        anagrams.sort();
+        assert_eq!(anagrams, ["act", "at", "cat", "ta"]);
+
+        let matching = |pattern: &[u8]| dawg.matching(pattern, usize::MAX);
+        assert_eq!(matching(b"c?t*"), ["cat", "cats"]);
+        assert_eq!(matching(b"**at"), ["at", "cat", "coat", "scat"]);
+        assert_eq!(
+            matching(b"*a*"),
+            ["act", "at", "cat", "cats", "coat", "scat", "ta"]
+        );
+        assert_eq!(matching(b"?"), Vec::<String>::new());
+        assert_eq!(dawg.matching(b"*", 3), ["act", "at", "cat"]);
+    }
+}
@@ -0,0 +1,175 @@
+//! Exact endgame search. Once the bag is empty, the player to move can work out their
+//! opponent's rack from the unseen tiles, so the rest of a two-player game has no hidden information.
+
+use super::{solve, Action, Board, Game, Tile};
+
+/// The best sequence of turns for both players from an endgame position.
+#[derive(Debug, Clone)]
+pub struct EndgameSolution {
+    /// Turns alternating between the player to move and their opponent, each a move or an
+    /// exchange, which with the bag empty gives the player back the same tiles
+    pub line: Vec<Action>,
+    /// Points the player to move gains over their opponent by the end of the line,
+    /// including the tiles left on each rack when the game ends
+    pub spread: i32,
+}
+
+impl Game {
+    /// Search every sequence of moves for both players with alpha-beta, up to `max_plies` turns deep.
+    /// The solution is exact if the game ends within that many turns, and otherwise values the position
+    /// at the horizon by the tiles left on each rack.
+    ///
+    /// Each turn is either a move or [`Game::exchange_tiles`], the only way to skip a turn. Going out
+    /// scores the opponent's remaining tiles as a bonus and costs the opponent the same, like
+    /// [`Game::play_move`], and is the only way the game ends. Forfeiting is never considered.
+    ///
+    /// Returns `None` unless the bag is empty and exactly two players are still playing.
+    pub fn solve_endgame(&self, max_plies: usize) -> Option<EndgameSolution> {
+        let active = self.players.iter().filter(|p| !p.forfeited).count();
+        if self.finished || !self.tile_bag.is_empty() || active != 2 {
+            return None;
+        }
+
+        // With the bag empty, the opponent's rack is exactly the tiles unseen by the player to move
+        let me = self.current_player();
+        let opponent = self
+            .players
+            .iter()
+            .find(|p| !p.forfeited && p.name != me.name)?;
+
+        let (spread, line) = search(
+            &self.board,
+            &me.tiles,
+            &opponent.tiles,
+            max_plies,
+            -i32::MAX,
+            i32::MAX,
+        );
+        Some(EndgameSolution { line, spread })
+    }
+}
+
+fn rack_value(rack: &[Tile]) -> i32 {
+    rack.iter().map(|t| t.point_value() as i32).sum()
+}
+
+/// Negamax with alpha-beta pruning. Returns the spread for the player holding `rack`, and the line that gets it.
+fn search(
+    board: &Board,
+    rack: &[Tile],
+    opponent: &[Tile],
+    depth: usize,
+    mut alpha: i32,
+    beta: i32,
+) -> (i32, Vec<Action>) {
+    if depth == 0 {
+        return (rack_value(opponent) - rack_value(rack), vec![]);
+    }
+
+    // Trying high scoring moves first makes for more cutoffs
+    let mut moves = solve::generate_moves(board, rack);
+    moves.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+
+    let mut best = (-i32::MAX, vec![]);
+    for (action, score) in moves
+        .into_iter()
+        .map(|(m, s)| (Action::PlayMove(m), s))
+        .chain([(Action::ExchangeTiles, 0)])
+    {
+        let (value, rest) = match &action {
+            Action::PlayMove(m) => {
+                let mut left = rack.to_vec();
+                for (_, t) in &m.tiles {
+                    let i = left.iter().position(|l| *l == t.as_tile()).unwrap();
+                    left.swap_remove(i);
+                }
+                if left.is_empty() {
+                    // Going out: we gain the opponent's tiles and they lose them
+                    (score as i32 + 2 * rack_value(opponent), vec![])
+                } else {
+                    let board = board.with_move_applied(m);
+                    let (v, rest) = search(&board, opponent, &left, depth - 1, -beta, -alpha);
+                    (score as i32 - v, rest)
+                }
+            }
+            // With nothing in the bag, exchanging keeps the same tiles and only gives up the turn
+            _ => {
+                let (v, rest) = search(board, opponent, rack, depth - 1, -beta, -alpha);
+                (-v, rest)
+            }
+        };
+
+        if value > best.0 {
+            let mut line = vec![action];
+            line.extend(rest);
+            best = (value, line);
+        }
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn endgame(board: &str, me: &[Tile], opponent: &[Tile]) -> Game {
+        let mut g = Game::with_seed(0);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+        g.board = Board::new().with_move_applied(&board.parse().unwrap());
+        g.players[0].tiles = me.to_vec();
+        g.players[1].tiles = opponent.to_vec();
+        g.tile_bag.clear();
+        g
+    }
+
+    #[test]
+    fn going_out_matches_play_move() {
+        let g = endgame("8H CAT", &[Tile::S], &[Tile::Q, Tile::Z]);
+        let solution = g.solve_endgame(4).unwrap();
+        let Action::PlayMove(first) = &solution.line[0] else {
+            panic!("expected a move, got {:?}", solution.line[0]);
+        };
+
+        let spread = |g: &Game| g.players[0].score() - g.players[1].score();
+        let mut played = g.clone();
+        played.play_move(first).unwrap();
+        assert!(played.is_finished());
+        assert_eq!(solution.spread, spread(&played) - spread(&g));
+        assert_eq!(solution.line.len(), 1);
+    }
+
+    #[test]
+    fn line_replays_to_spread() {
+        // Alice can't go out in one, so the line has to include Bob's reply
+        let g = endgame("8H CAT", &[Tile::Q, Tile::V, Tile::S], &[Tile::A, Tile::T]);
+        let solution = g.solve_endgame(6).unwrap();
+        assert!(solution.line.len() >= 2);
+
+        let spread = |g: &Game| g.players[0].score() - g.players[1].score();
+        let mut played = g.clone();
+        for action in &solution.line {
+            match action {
+                Action::PlayMove(m) => played.play_move(m).unwrap(),
+                Action::ExchangeTiles => played.exchange_tiles(),
+                Action::Forfeit(_) => unreachable!(),
+            }
+        }
+        assert!(played.is_finished());
+        assert_eq!(solution.spread, spread(&played) - spread(&g));
+    }
+
+    #[test]
+    fn not_an_endgame() {
+        let mut g = Game::with_seed(0);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+        assert!(g.solve_endgame(2).is_none());
+    }
+}
@@ -0,0 +1,236 @@
+//! Move ranking by equity: the points a move scores now, plus the value of the tiles it leaves
+//! on the rack for later. Keeping an S or a blank is worth more than the couple of points
+//! gained by playing it, and keeping a Q or three I's costs points in the long run.
+//!
+//! Leave values are read from `leaves.txt`, one leave per line, such as `?S 31.5`, with `?` for
+//! a blank. The file is built by [`build_leaves`] from self-play, see `server build-leaves`.
+
+use std::{collections::HashMap, fmt::Write, sync::LazyLock};
+
+use itertools::Itertools;
+
+use super::{solve, Board, Game, Move, Tile};
+
+/// The leave values shipped in `leaves.txt`
+pub static LEAVES: LazyLock<Leaves> =
+    LazyLock::new(|| Leaves::parse(include_str!("../../leaves.txt")).unwrap());
+
+/// Values of leaves, in points. Leaves of one or two tiles are looked up directly,
+/// and longer leaves are valued by their single tiles and the pairs within them.
+#[derive(Debug, Clone, Default)]
+pub struct Leaves {
+    values: HashMap<String, f64>,
+}
+
+/// A move along with what it's worth.
+#[derive(Debug, Clone)]
+pub struct RankedMove {
+    /// The move
+    pub m: Move,
+    /// Points the move scores
+    pub score: u32,
+    /// Tiles left on the rack after the move
+    pub leave: Vec<Tile>,
+    /// The score plus the value of the leave
+    pub equity: f64,
+}
+
+/// Sorted so that every arrangement of the same tiles has the same key, with `?` for blanks
+fn leave_key(tiles: &[Tile]) -> String {
+    tiles
+        .iter()
+        .map(|t| match t {
+            Tile::Blank => '?',
+            t => t.as_char().to_ascii_uppercase(),
+        })
+        .sorted()
+        .collect()
+}
+
+impl Leaves {
+    /// Read leave values in the format described above, ignoring blank lines and `#` comments
+    pub fn parse(s: &str) -> Result<Leaves, String> {
+        let mut values = HashMap::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line.split_once(' ').and_then(|(leave, value)| {
+                let valid = leave.chars().all(|c| c.is_ascii_uppercase() || c == '?');
+                Some((valid.then_some(leave)?, value.trim().parse::<f64>().ok()?))
+            });
+            let (leave, value) =
+                parsed.ok_or_else(|| format!("Invalid leave on line {}: {line:?}", i + 1))?;
+            values.insert(leave.chars().sorted().collect(), value);
+        }
+        Ok(Leaves { values })
+    }
+
+    /// The file format read by [`Leaves::parse`], singles then pairs, most valuable first
+    pub fn to_file_string(&self) -> String {
+        let mut out = String::new();
+        let sorted = self
+            .values
+            .iter()
+            .sorted_by(|(a, x), (b, y)| a.len().cmp(&b.len()).then(y.total_cmp(x)).then(a.cmp(b)));
+        for (leave, value) in sorted {
+            writeln!(out, "{leave} {value:.2}").unwrap();
+        }
+        out
+    }
+
+    /// What keeping `leave` on the rack is worth, in points
+    pub fn value(&self, leave: &[Tile]) -> f64 {
+        let key = leave_key(leave);
+        if let Some(v) = self.values.get(&key) {
+            return *v;
+        }
+
+        let single = |c: char| self.values.get(&c.to_string()).copied().unwrap_or(0.0);
+        let singles: f64 = key.chars().map(single).sum();
+        let synergies: f64 = key
+            .chars()
+            .tuple_combinations()
+            .filter_map(|(a, b)| {
+                let pair = self.values.get(&format!("{a}{b}"))?;
+                Some(pair - single(a) - single(b))
+            })
+            .sum();
+        singles + synergies
+    }
+}
+
+/// Every valid move with `rack`, best first
+pub fn rank_moves(board: &Board, rack: &[Tile], leaves: &Leaves) -> Vec<RankedMove> {
+    let mut ranked: Vec<_> = solve::generate_moves(board, rack)
+        .into_iter()
+        .map(|(m, score)| {
+            let mut leave = rack.to_vec();
+            for (_, t) in &m.tiles {
+                let i = leave.iter().position(|l| *l == t.as_tile()).unwrap();
+                leave.swap_remove(i);
+            }
+            let equity = score as f64 + leaves.value(&leave);
+            RankedMove {
+                m,
+                score,
+                leave,
+                equity,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.equity.total_cmp(&a.equity).then_with(|| a.m.cmp(&b.m)));
+    ranked
+}
+
+impl Game {
+    /// Every move the current player can make, best first
+    pub fn ranked_moves(&self, leaves: &Leaves) -> Vec<RankedMove> {
+        rank_moves(&self.board, &self.current_player().tiles, leaves)
+    }
+}
+
+/// Learn leave values by having two players play `games` games against each other,
+/// each time keeping the leave with the best equity according to `leaves`. A leave is worth
+/// how much more than average its holder scores on their next turn.
+pub fn build_leaves(leaves: &Leaves, games: u64, seed: u64) -> Leaves {
+    /// Leaves seen fewer times than this are too noisy to keep
+    const MIN_SAMPLES: u32 = 50;
+
+    // Next-turn scores, by every single tile and pair of tiles in the leave they followed
+    let mut samples: HashMap<String, (f64, u32)> = HashMap::new();
+    let mut total = (0.0, 0);
+
+    for i in 0..games {
+        let mut g = Game::with_seed(seed.wrapping_add(i));
+        g.add_player("A");
+        g.add_player("B");
+        g.start_game(false);
+
+        let mut pending: [Option<String>; 2] = [None, None];
+        let mut passes = 0;
+        while !g.finished && passes < 6 {
+            let player = g.whose_turn;
+            let best = g.ranked_moves(leaves).into_iter().next();
+            let (score, leave) = match best {
+                Some(best) => {
+                    g.play_move(&best.m).unwrap();
+                    passes = 0;
+                    (best.score, best.leave)
+                }
+                None => {
+                    g.exchange_tiles();
+                    passes += 1;
+                    (0, vec![])
+                }
+            };
+
+            if let Some(key) = pending[player].take() {
+                let chars: Vec<char> = key.chars().collect();
+                let subleaves = chars
+                    .iter()
+                    .map(|c| c.to_string())
+                    .chain(
+                        chars
+                            .iter()
+                            .tuple_combinations()
+                            .map(|(a, b)| format!("{a}{b}")),
+                    )
+                    .unique();
+                for sub in subleaves {
+                    let (sum, n) = samples.entry(sub).or_default();
+                    *sum += score as f64;
+                    *n += 1;
+                }
+                total.0 += score as f64;
+                total.1 += 1;
+            }
+            pending[player] = Some(leave_key(&leave));
+        }
+    }
+
+    let average = total.0 / total.1.max(1) as f64;
+    let values = samples
+        .into_iter()
+        .filter(|(_, (_, n))| *n >= MIN_SAMPLES)
+        .map(|(key, (sum, n))| (key, sum / n as f64 - average))
+        .collect();
+    Leaves { values }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leave_values() {
+        let leaves = Leaves::parse("# comment\n? 25\nS 8\nQ -7\nSS 12\n").unwrap();
+        assert_eq!(leaves.value(&[Tile::S, Tile::Blank]), 33.0);
+        assert_eq!(leaves.value(&[Tile::S, Tile::S]), 12.0);
+        // SS is worth 4 less than two separate S's
+        assert_eq!(
+            leaves.value(&[Tile::S, Tile::Q, Tile::S]),
+            8.0 + 8.0 - 7.0 - 4.0
+        );
+        assert_eq!(leaves.value(&[]), 0.0);
+        Leaves::parse("S eight").unwrap_err();
+
+        let round_trip = Leaves::parse(&leaves.to_file_string()).unwrap();
+        assert_eq!(round_trip.values, leaves.values);
+    }
+
+    #[test]
+    fn rank_by_equity() {
+        use Tile::*;
+
+        let leaves = Leaves::parse("? 30\n").unwrap();
+        let ranked = rank_moves(&Board::new(), &[C, A, T, Blank], &leaves);
+        assert!(ranked.windows(2).all(|w| w[0].equity >= w[1].equity));
+        // Playing CAT and keeping the blank beats spending the blank to play a longer word
+        assert_eq!(ranked[0].leave, [Blank]);
+        assert_eq!(ranked[0].equity, ranked[0].score as f64 + 30.0);
+
+        assert!(!LEAVES.values.is_empty());
+    }
+}
@@ -1,42 +1,24 @@
-use std::{
-    collections::HashMap,
-    io::{self, BufRead, BufReader},
-    sync::LazyLock,
-};
-
-use super::{Board, BoardTile, InvalidMove, Move, Position};
-use itertools::Itertools;
-
-pub static WORDLIST: LazyLock<Vec<String>> = LazyLock::new(|| {
-    let reader;
-
-    #[cfg(debug_assertions)]
-    {
-        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("words.txt.zst");
-        reader = std::fs::File::open(path).unwrap();
-    }
-
-    #[cfg(not(debug_assertions))]
-    {
-        reader = &include_bytes!("../../words.txt.zst")[..];
-    }
+//! Word checking, move scoring and move generation against the built-in word list.
 
-    let buf_reader = BufReader::new(zstd::Decoder::new(reader).unwrap());
-    let words = buf_reader
-        .lines()
-        .collect::<io::Result<Vec<String>>>()
-        .unwrap();
+use std::{collections::HashMap, iter, sync::LazyLock};
 
-    assert!(words.windows(2).all(|w| w[0] < w[1]));
-    assert!(words
-        .iter()
-        .all(|w| w.chars().all(|c| c.is_ascii_lowercase())));
+use super::{
+    dawg::{Dawg, Node},
+    equity::{RankedMove, LEAVES},
+    unseen::UnseenTiles,
+    Axis, Board, BoardTile, Game, InvalidMove, Letter, Move, Position, Tile,
+};
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
-    words
-});
+/// The built-in word list, built into a graph by `build.rs`
+pub static WORDLIST: LazyLock<Dawg> =
+    LazyLock::new(|| Dawg::from_bytes(include_bytes!(concat!(env!("OUT_DIR"), "/words.dawg"))));
 
+/// Whether `s`, in lowercase, is in the built-in word list
 pub fn is_word(s: &str) -> bool {
-    WORDLIST.binary_search_by_key(&s, |w| w.as_str()).is_ok()
+    WORDLIST.contains(s)
 }
 
 /// Includes the center as a double word modifier.
@@ -59,14 +41,21 @@ pub static MODIFIERS: LazyLock<HashMap<Position, Modifier>> = LazyLock::new(|| {
     HashMap::from_iter(iter)
 });
 
+/// A premium square, which multiplies the tile placed on it or the word through it.
 #[derive(Clone, Copy, Debug)]
 pub enum Modifier {
+    /// Doubles the tile's points
     DoubleLetter,
+    /// Triples the tile's points
     TripleLetter,
+    /// Doubles the word's points
     DoubleWord,
+    /// Triples the word's points
     TripleWord,
 }
 
+/// Check where a move's tiles go: at most seven, on empty squares in one straight line, and the first
+/// move through the center. Contiguity and words are checked by [`Board::play_move`].
 pub fn validate_move(board: &Board, m: &Move) -> Result<(), InvalidMove> {
     if m.tiles.is_empty() {
         return Err(InvalidMove::new("Empty move (impossible)", vec![]));
@@ -79,6 +68,13 @@ pub fn validate_move(board: &Board, m: &Move) -> Result<(), InvalidMove> {
         ));
     }
 
+    if m.positions().any(|(x, y)| x >= 15 || y >= 15) {
+        return Err(InvalidMove::new(
+            "Move is off the board (impossible)",
+            vec![],
+        ));
+    }
+
     if let Some(pair) = m.tiles.iter().combinations(2).find(|ts| ts[0].0 == ts[1].0) {
         return Err(InvalidMove::new(
             "Move is self-overlapping (impossible)",
@@ -125,6 +121,7 @@ pub fn validate_move(board: &Board, m: &Move) -> Result<(), InvalidMove> {
     Ok(())
 }
 
+/// Points for a valid move, counting every word it forms and the bonus for using all seven tiles
 pub fn score_move(board: &Board, m: &Move) -> u32 {
     use Modifier::*;
 
@@ -159,8 +156,25 @@ pub fn score_move(board: &Board, m: &Move) -> u32 {
     score * word_multiplier + all_tiles_bonus
 }
 
-/// Preconditions: m is not empty, m is not a detached single letter, m doesn't overlap a previous move on the board
-pub fn expand_move(board: &Board, m: &Move) -> (Move, Vec<Move>) {
+/// Empty squares between the first and last tiles of a move.
+/// Precondition: m is a non-empty straight line
+pub fn find_gaps(board: &Board, m: &Move) -> Vec<Position> {
+    let m = m.sorted();
+    let (first, last) = (m.tiles[0].0, m.tiles[m.tiles.len() - 1].0);
+    let line: Vec<Position> = if first.0 == last.0 {
+        (first.1..=last.1).map(|y| (first.0, y)).collect()
+    } else {
+        (first.0..=last.0).map(|x| (x, first.1)).collect()
+    };
+    line.into_iter()
+        .filter(|&(x, y)| board[x][y].is_none() && !m.contains_position((x, y)))
+        .collect()
+}
+
+/// Returns the main word formed by the move, any words it forms crossing the main word,
+/// and the axis the main word was read along.
+/// Preconditions: m is not empty, m doesn't overlap a previous move on the board
+pub fn expand_move(board: &Board, m: &Move) -> (Move, Vec<Move>, Axis) {
     /// Extend the given move to include tiles before and after it
     /// eg PAIN[TER] -> [PAINTER]
     fn expand_move_in_axis(board: &Board, m: &Move, (dx, dy): (isize, isize)) -> Move {
@@ -211,19 +225,23 @@ pub fn expand_move(board: &Board, m: &Move) -> (Move, Vec<Move>) {
         let m_horizontal = expand_move_in_axis(board, m, (0, 1));
 
         if m_vertical.tiles.len() == 1 && m_horizontal.tiles.len() == 1 {
-            (m_vertical, vec![])
+            (m_vertical, vec![], Axis::Vertical)
+        } else if m_horizontal.tiles.len() > 1 {
+            // Prefer reading across when the tile forms words both ways
+            let crossing = if m_vertical.tiles.len() > 1 {
+                vec![m_vertical]
+            } else {
+                vec![]
+            };
+            (m_horizontal, crossing, Axis::Horizontal)
         } else {
-            let mut moves = vec![m_vertical, m_horizontal];
-            moves.retain(|m| m.tiles.len() > 1);
-            assert!(!moves.is_empty());
-            let main_word_move = moves.pop().unwrap();
-            (main_word_move, moves)
+            (m_vertical, vec![], Axis::Vertical)
         }
     } else {
-        let (parallel, perpendicular) = if m.is_horizontal() {
-            ((0, 1), (1, 0))
+        let (parallel, perpendicular, axis) = if m.is_horizontal() {
+            ((0, 1), (1, 0), Axis::Horizontal)
         } else {
-            ((1, 0), (0, 1))
+            ((1, 0), (0, 1), Axis::Vertical)
         };
 
         // Expand the main word
@@ -241,18 +259,374 @@ pub fn expand_move(board: &Board, m: &Move) -> (Move, Vec<Move>) {
             }
         }
 
-        (main_word_move, crossing_words)
+        (main_word_move, crossing_words, axis)
     }
 }
 
+/// Total score of a move, including every word it forms.
+/// Preconditions: same as `expand_move`
+pub fn score_play(board: &Board, m: &Move) -> u32 {
+    let (main_word, crossing_words, _) = expand_move(board, m);
+    iter::once(&main_word)
+        .chain(&crossing_words)
+        .map(|w| score_move(board, w))
+        .sum()
+}
+
+/// Every valid move that can be made on `board` with the tiles in `rack`, along with its score.
+/// Blanks are tried as every letter. Moves are found by walking the dictionary along each row
+/// and column, so this is fast enough to call once per turn for a computer player.
+pub fn generate_moves(board: &Board, rack: &[Tile]) -> Vec<(Move, u32)> {
+    let mut counts = [0u8; 27];
+    for t in rack {
+        counts[rack_index(*t)] += 1;
+    }
+
+    let mut moves = Vec::new();
+    for axis in [Axis::Horizontal, Axis::Vertical] {
+        // Columns are generated as the rows of the transposed board
+        let b = match axis {
+            Axis::Horizontal => *board,
+            Axis::Vertical => transpose(board),
+        };
+        let first_move = b.is_empty();
+        for x in 0..15 {
+            let mut line = LineGenerator {
+                board: &b,
+                x,
+                axis,
+                cross_checks: [ALL_LETTERS; 15],
+                has_crossing: [false; 15],
+                anchors: [false; 15],
+                counts,
+                placed: Vec::with_capacity(7),
+                out: &mut moves,
+            };
+            line.prepare(first_move);
+            line.generate();
+        }
+    }
+
+    moves
+        .into_iter()
+        .map(|m| {
+            let score = score_play(board, &m);
+            (m, score)
+        })
+        .collect()
+}
+
+const ALL_LETTERS: u32 = (1 << 26) - 1;
+
+/// Index into a rack's tile counts, with blanks last
+fn rack_index(t: Tile) -> usize {
+    match t {
+        Tile::Blank => 26,
+        t => (t.as_ascii() - b'a') as usize,
+    }
+}
+
+fn transpose(board: &Board) -> Board {
+    let mut t = Board::new();
+    for x in 0..15 {
+        for y in 0..15 {
+            t[y][x] = board[x][y];
+        }
+    }
+    t
+}
+
+/// Generates the moves along one row of a board
+struct LineGenerator<'a> {
+    board: &'a Board,
+    x: usize,
+    axis: Axis,
+    /// Letters that form valid crossing words at each empty square, as bits
+    cross_checks: [u32; 15],
+    /// Whether each square has tiles above or below it
+    has_crossing: [bool; 15],
+    /// Empty squares next to tiles, one of which every move must cover
+    anchors: [bool; 15],
+    counts: [u8; 27],
+    placed: Vec<(Position, BoardTile)>,
+    out: &'a mut Vec<Move>,
+}
+
+impl LineGenerator<'_> {
+    fn prepare(&mut self, first_move: bool) {
+        let (board, x) = (self.board, self.x);
+        if first_move {
+            self.anchors[7] = x == 7;
+            return;
+        }
+
+        for y in (0..15).filter(|y| board[x][*y].is_none()) {
+            let mut above = String::new();
+            let mut i = x;
+            while i > 0 && board[i - 1][y].is_some() {
+                i -= 1;
+                above.insert(0, board[i][y].unwrap().as_letter().as_char());
+            }
+            let mut below = String::new();
+            let mut i = x;
+            while i < 14 && board[i + 1][y].is_some() {
+                i += 1;
+                below.push(board[i][y].unwrap().as_letter().as_char());
+            }
+
+            let left = y > 0 && board[x][y - 1].is_some();
+            let right = y < 14 && board[x][y + 1].is_some();
+            self.has_crossing[y] = !above.is_empty() || !below.is_empty();
+            self.anchors[y] = self.has_crossing[y] || left || right;
+
+            if self.has_crossing[y] {
+                self.cross_checks[y] = (b'a'..=b'z')
+                    .filter(|c| is_word(&format!("{above}{}{below}", char::from(*c))))
+                    .fold(0, |mask, c| mask | 1 << (c - b'a'));
+            }
+        }
+    }
+
+    fn generate(&mut self) {
+        let tiles_in_rack: usize = self.counts.iter().map(|n| *n as usize).sum();
+        for start in 0..15 {
+            if start > 0 && self.board[self.x][start - 1].is_some() {
+                continue;
+            }
+            // Only start where the rack can reach an anchor
+            let Some(anchor) = (start..15).find(|y| self.anchors[*y]) else {
+                break;
+            };
+            let empty_before_anchor = (start..anchor)
+                .filter(|y| self.board[self.x][*y].is_none())
+                .count();
+            if empty_before_anchor < tiles_in_rack {
+                self.extend(start, start, WORDLIST.root(), false);
+            }
+        }
+    }
+
+    fn extend(&mut self, start: usize, y: usize, node: Node, anchored: bool) {
+        if let Some(tile) = self
+            .board
+            .0
+            .get(self.x)
+            .and_then(|row| row.get(y))
+            .copied()
+            .flatten()
+        {
+            if let Some(next) = WORDLIST.child(node, tile.as_letter().as_ascii()) {
+                self.extend(start, y + 1, next, anchored);
+            }
+            return;
+        }
+
+        if node.is_terminal() && anchored && y - start >= 2 {
+            self.record();
+        }
+
+        if y == 15 {
+            return;
+        }
+
+        for (letter, next) in WORDLIST.children(node) {
+            let i = letter - b'a';
+            if self.cross_checks[y] & 1 << i == 0 {
+                continue;
+            }
+            let anchored = anchored || self.anchors[y];
+            for (index, tile) in [
+                (i as usize, BoardTile::from(Tile::from_ascii(letter))),
+                (26, BoardTile::Blank(Letter::from_ascii(letter))),
+            ] {
+                if self.counts[index] == 0 {
+                    continue;
+                }
+                self.counts[index] -= 1;
+                self.placed.push(((self.x, y), tile));
+                self.extend(start, y + 1, next, anchored);
+                self.placed.pop();
+                self.counts[index] += 1;
+            }
+        }
+    }
+
+    fn record(&mut self) {
+        let tiles = match self.axis {
+            Axis::Horizontal => self.placed.clone(),
+            Axis::Vertical => {
+                // A lone tile with neighbours across was already found as part of that row's word
+                if let [((_, y), _)] = self.placed[..] {
+                    if self.has_crossing[y] {
+                        return;
+                    }
+                }
+                self.placed
+                    .iter()
+                    .map(|((x, y), t)| ((*y, *x), *t))
+                    .collect()
+            }
+        };
+        self.out.push(Move::new(tiles));
+    }
+}
+
+/// Settings for [`simulate`].
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    /// How many of the best moves by equity to compare
+    pub candidates: usize,
+    /// How many times to play out each candidate, each with different opponent racks and draws
+    pub iterations: usize,
+    /// Turns to play out after each candidate, starting with the next player's
+    pub plies: usize,
+    /// The same seed always gives the same results, however many threads are used
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            candidates: 10,
+            iterations: 100,
+            plies: 2,
+            seed: 0,
+        }
+    }
+}
+
+/// A candidate move and how it did in simulation.
+#[derive(Debug, Clone)]
+pub struct SimResult {
+    /// The candidate move
+    pub m: Move,
+    /// Points the move scores
+    pub score: u32,
+    /// The move's equity, see [`RankedMove`]
+    pub equity: f64,
+    /// Average of the current player's points minus their best opponent's over the played out turns,
+    /// counting the value of each player's leave at the end
+    pub spread: f64,
+}
+
+/// Rank the current player's best moves by simulation: for each candidate, repeatedly deal the opponents
+/// random racks from the unseen tiles, play the candidate, and let everyone play their best move by equity
+/// for a few turns. Runs on every core. Results are sorted best first.
+pub fn simulate(game: &Game, config: &SimConfig) -> Vec<SimResult> {
+    if game.finished {
+        return vec![];
+    }
+    let candidates: Vec<RankedMove> = game
+        .ranked_moves(&LEAVES)
+        .into_iter()
+        .take(config.candidates)
+        .collect();
+    let me = game.whose_turn;
+    let unseen = game.unseen_tiles(game.players[me].name()).unwrap();
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    // Spreads of every candidate, by iteration, so that they are summed in the same order every time
+    let mut spreads = vec![vec![]; config.iterations];
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let (candidates, unseen) = (&candidates, &unseen);
+                scope.spawn(move || {
+                    (t..config.iterations)
+                        .step_by(threads)
+                        .map(|i| {
+                            let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+                            rng.set_stream(i as u64);
+                            let start = deal_unseen(game, unseen, &mut rng);
+                            let spreads = candidates
+                                .iter()
+                                .map(|c| play_out(&start, &c.m, config.plies))
+                                .collect();
+                            (i, spreads)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for h in handles {
+            for (i, s) in h.join().unwrap() {
+                spreads[i] = s;
+            }
+        }
+    });
+
+    let mut results: Vec<_> = candidates
+        .into_iter()
+        .enumerate()
+        .map(|(c, ranked)| SimResult {
+            spread: spreads.iter().map(|s| s[c]).sum::<f64>() / config.iterations.max(1) as f64,
+            m: ranked.m,
+            score: ranked.score,
+            equity: ranked.equity,
+        })
+        .collect();
+    results.sort_by(|a, b| b.spread.total_cmp(&a.spread));
+    results
+}
+
+/// A copy of the game where the opponents' racks and the bag are a random guess from the unseen tiles
+fn deal_unseen(game: &Game, unseen: &UnseenTiles, rng: &mut ChaCha8Rng) -> Game {
+    let mut g = game.snapshot();
+    let mut pool: Vec<Tile> = unseen
+        .counts
+        .iter()
+        .flat_map(|(t, n)| iter::repeat_n(*t, *n))
+        .collect();
+    pool.shuffle(rng);
+    for (i, p) in g.players.iter_mut().enumerate() {
+        if i != game.whose_turn {
+            let n = p.tiles.len();
+            p.tiles = pool.split_off(pool.len() - n);
+        }
+    }
+    g.tile_bag = pool;
+    g.rng = ChaCha8Rng::seed_from_u64(rng.gen());
+    g
+}
+
+/// Play `m` and then `plies` more turns, returning the spread for the player who played `m`
+fn play_out(start: &Game, m: &Move, plies: usize) -> f64 {
+    let me = start.whose_turn;
+    let mut g = start.snapshot();
+    g.play_move(m).unwrap();
+    for _ in 0..plies {
+        if g.finished {
+            break;
+        }
+        match g.ranked_moves(&LEAVES).into_iter().next() {
+            Some(best) => g.play_move(&best.m).unwrap(),
+            None => g.exchange_tiles(),
+        }
+    }
+
+    let value = |i: usize| {
+        let gained = (g.players[i].score() - start.players[i].score()) as f64;
+        let leave = if g.finished {
+            0.0
+        } else {
+            LEAVES.value(&g.players[i].tiles)
+        };
+        gained + leave
+    };
+    let best_opponent = (0..g.players.len())
+        .filter(|i| *i != me && !g.players[*i].forfeited)
+        .map(value)
+        .max_by(f64::total_cmp)
+        .unwrap_or(0.0);
+    value(me) - best_opponent
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn expand_move_test() {
-        use BoardTile::*;
-
         fn test(
             premoves: impl IntoIterator<Item = Move>,
             m: Move,
@@ -265,7 +639,7 @@ mod test {
                 b = b.with_move_applied(&pm);
             }
 
-            let (expanded_move, mut crossing_moves) = expand_move(&b, &m);
+            let (expanded_move, mut crossing_moves, _) = expand_move(&b, &m);
             crossing_moves.push(expanded_move);
 
             fn sort(ms: impl IntoIterator<Item = Move>) -> HashSet<Move> {
@@ -276,21 +650,82 @@ mod test {
             assert_eq!(sort(expected_moves), sort(crossing_moves));
         }
 
-        macro_rules! m {
-        ($(($x:expr, $y:expr, $t:expr)),*) => { Move::new(vec![$((($x, $y), $t)),*]) }
+        let m = |s: &str| s.parse::<Move>().unwrap();
+        test([], m("1A AB"), [m("1A AB")]);
+        test([m("1A X")], m("2A Y"), [m("A1 XY")]);
+        test([m("11K OAT")], m("M9 NU"), [m("M9 NUT")]);
     }
 
-        test([], m![(0, 0, A), (0, 1, B)], [m![(0, 0, A), (0, 1, B)]]);
-        test([m![(0, 0, X)]], m![(1, 0, Y)], [m![(0, 0, X), (1, 0, Y)]]);
-        test(
-            [m![(10, 10, O), (10, 11, A), (10, 12, T)]],
-            m![(8, 12, N), (9, 12, U)],
-            [m![(8, 12, N), (9, 12, U), (10, 12, T)]],
-        );
+    #[test]
+    fn find_gaps_test() {
+        let b = Board::new().with_move_applied(&"4F A".parse().unwrap());
+        let m = "4C B.D..C".parse().unwrap();
+        assert_eq!(find_gaps(&b, &m), [(3, 3), (3, 6)]);
     }
 
     #[test]
     fn load_wordlist() {
-        LazyLock::force(&WORDLIST);
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("words.txt");
+        let words = std::fs::read_to_string(path).unwrap();
+        assert!(words.lines().all(is_word));
+        assert!(!is_word("zzzq"));
+        assert!(!is_word(""));
+    }
+
+    #[test]
+    fn generate_moves_test() {
+        use Tile::*;
+
+        // Opening moves must cross the center, and every one is accepted by the board
+        let rack = [C, A, T, S, Blank, Q, E];
+        let moves = generate_moves(&Board::new(), &rack);
+        assert!(!moves.is_empty());
+        for (m, score) in moves.iter().step_by(97) {
+            let mut b = Board::new();
+            let played = b.play_move(m).unwrap();
+            let total: u32 = played.word_values.iter().map(|(_, v)| v).sum();
+            assert_eq!(total, *score);
+        }
+        let cats = "8H CATS".parse().unwrap();
+        assert!(moves.iter().any(|(m, _)| *m == cats));
+
+        // Moves off an existing word are in both directions, and single tiles aren't repeated
+        let b = Board::new().with_move_applied(&cats);
+        let moves = generate_moves(&b, &[O, X]);
+        for (m, _) in &moves {
+            b.clone().play_move(m).unwrap();
+        }
+        assert_eq!(moves.iter().map(|(m, _)| m).unique().count(), moves.len());
+        let ox_down = "I7 OX".parse().unwrap();
+        assert!(!moves.iter().any(|(m, _)| *m == ox_down));
+        let ax = "9I X".parse().unwrap();
+        assert!(moves.iter().any(|(m, _)| *m == ax));
+    }
+
+    #[test]
+    fn simulate_test() {
+        let mut g = Game::with_seed(1);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+
+        let config = SimConfig {
+            candidates: 3,
+            iterations: 5,
+            plies: 1,
+            seed: 7,
+        };
+        let results = simulate(&g, &config);
+        assert_eq!(results.len(), 3);
+        assert!(results.windows(2).all(|w| w[0].spread >= w[1].spread));
+
+        // Same seed, same answer
+        let again = simulate(&g, &config);
+        let spreads = |rs: &[SimResult]| {
+            rs.iter()
+                .map(|r| (r.m.clone(), r.spread))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spreads(&results), spreads(&again));
     }
 }
@@ -0,0 +1,197 @@
+//! Tile tracking from one player's point of view, and the odds that follow from it.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{Game, Tile};
+
+/// The tiles a player can't see: everything not on the board or in their own rack.
+/// Each of these is either in the bag or in an opponent's rack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "UnseenTilesT")
+)]
+pub struct UnseenTiles {
+    /// How many of each tile are unseen, A to Z then blanks, skipping tiles with none left
+    pub counts: Vec<(Tile, usize)>,
+    /// How many of them are in the bag
+    pub in_bag: usize,
+    /// How many tiles each opponent is holding
+    pub opponents: Vec<(String, usize)>,
+}
+
+/// A probability a player can ask about.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TileQuestionT")
+)]
+pub enum TileQuestion {
+    /// The chance of drawing at least one `tile` when drawing `draws` tiles from the bag
+    Draw {
+        /// The tile hoped for
+        tile: Tile,
+        /// How many tiles are drawn
+        draws: usize,
+    },
+    /// The chance that `player` holds at least one `tile`
+    Holds {
+        /// The tile asked about
+        tile: Tile,
+        /// The opponent asked about
+        player: String,
+    },
+}
+
+/// The answer to a [`TileQuestion`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TileOddsT")
+)]
+pub struct TileOdds {
+    /// What was asked
+    pub question: TileQuestion,
+    /// The probability, from 0 to 1
+    pub chance: f64,
+}
+
+impl Game {
+    /// Tiles unseen by the named player, or `None` if they aren't in the game
+    pub fn unseen_tiles(&self, name: &str) -> Option<UnseenTiles> {
+        let player = self.players.iter().find(|p| p.name == name)?;
+
+        let mut counts: HashMap<Tile, usize> = HashMap::new();
+        for t in Tile::iter_game_count() {
+            *counts.entry(t).or_insert(0) += 1;
+        }
+        let on_board = self
+            .board
+            .0
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|bt| bt.as_tile());
+        for t in on_board.chain(player.tiles.iter().copied()) {
+            *counts.get_mut(&t).unwrap() -= 1;
+        }
+
+        Some(UnseenTiles {
+            counts: Tile::iter_game_count()
+                .dedup()
+                .map(|t| (t, counts[&t]))
+                .filter(|(_, n)| *n > 0)
+                .collect(),
+            in_bag: self.tile_bag.len(),
+            opponents: self
+                .players
+                .iter()
+                .filter(|p| p.name != name && !p.forfeited)
+                .map(|p| (p.name.clone(), p.tiles.len()))
+                .collect(),
+        })
+    }
+}
+
+impl UnseenTiles {
+    /// How many tiles are unseen in all
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, n)| n).sum()
+    }
+
+    /// How many of `tile` are unseen
+    pub fn count(&self, tile: Tile) -> usize {
+        self.counts
+            .iter()
+            .find(|(t, _)| *t == tile)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    /// The chance of drawing at least one `tile` when drawing `draws` tiles.
+    /// The bag is a random selection of the unseen tiles, so this is the same as drawing from all of them.
+    pub fn draw_chance(&self, tile: Tile, draws: usize) -> f64 {
+        1.0 - none_chance(self.total(), self.count(tile), draws.min(self.in_bag))
+    }
+
+    /// The chance that an opponent with `rack_size` tiles holds at least one `tile`.
+    /// Once the bag is empty, every unseen tile is in an opponent's rack.
+    pub fn holds_chance(&self, tile: Tile, rack_size: usize) -> f64 {
+        1.0 - none_chance(self.total(), self.count(tile), rack_size)
+    }
+
+    /// Answer a question, or `None` if it asks about someone who isn't an opponent
+    pub fn answer(&self, question: &TileQuestion) -> Option<f64> {
+        match question {
+            TileQuestion::Draw { tile, draws } => Some(self.draw_chance(*tile, *draws)),
+            TileQuestion::Holds { tile, player } => {
+                let (_, rack_size) = self.opponents.iter().find(|(name, _)| name == player)?;
+                Some(self.holds_chance(*tile, *rack_size))
+            }
+        }
+    }
+}
+
+/// The chance that picking `picks` of `total` things without replacement avoids all `count` special ones
+fn none_chance(total: usize, count: usize, picks: usize) -> f64 {
+    (0..picks.min(total))
+        .map(|i| total.saturating_sub(count + i) as f64 / (total - i) as f64)
+        .product()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn none_chance_test() {
+        assert_eq!(none_chance(4, 2, 2), 1.0 / 6.0);
+        assert_eq!(none_chance(4, 0, 2), 1.0);
+        assert_eq!(none_chance(3, 1, 3), 0.0);
+        assert_eq!(none_chance(5, 1, 0), 1.0);
+    }
+
+    #[test]
+    fn unseen_tiles() {
+        let mut g = Game::with_seed(0);
+        g.add_player("Alice");
+        g.add_player("Bob");
+        g.start_game(false);
+
+        let unseen = g.unseen_tiles("Alice").unwrap();
+        assert_eq!(unseen.total(), 100 - 7);
+        assert_eq!(unseen.in_bag, 100 - 14);
+        assert_eq!(unseen.opponents, [("Bob".to_string(), 7)]);
+        let alice_blanks = g.players[0]
+            .tiles
+            .iter()
+            .filter(|t| **t == Tile::Blank)
+            .count();
+        assert_eq!(unseen.count(Tile::Blank), 2 - alice_blanks);
+        assert!(g.unseen_tiles("Nobody").is_none());
+
+        // With the bag empty, the only opponent holds every unseen tile
+        let unseen = UnseenTiles {
+            counts: vec![(Tile::A, 1), (Tile::Q, 2)],
+            in_bag: 0,
+            opponents: vec![("Bob".into(), 3)],
+        };
+        assert_eq!(unseen.draw_chance(Tile::A, 7), 0.0);
+        let bob = |tile| TileQuestion::Holds {
+            tile,
+            player: "Bob".into(),
+        };
+        assert_eq!(unseen.answer(&bob(Tile::A)), Some(1.0));
+        assert_eq!(unseen.answer(&bob(Tile::Z)), Some(0.0));
+        let charlie = TileQuestion::Holds {
+            tile: Tile::A,
+            player: "Charlie".into(),
+        };
+        assert_eq!(unseen.answer(&charlie), None);
+    }
+}
@@ -0,0 +1,17 @@
+//! A Scrabble game engine and move solver, along with the tables and messages used to host games online.
+//!
+//! The engine has no networking dependencies. Build with `default-features = false` to leave out
+//! the `server` and `tui` binaries and the tokio/axum stack they need.
+//!
+//! ```
+//! use scrabble::game::{solve::generate_moves, Board, Tile};
+//!
+//! let moves = generate_moves(&Board::default(), &[Tile::C, Tile::A, Tile::T]);
+//! assert!(moves.iter().any(|(m, score)| m.to_string() == "8F CAT" && *score == 10));
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod game;
+pub mod protocol;
+pub mod table;
@@ -0,0 +1,243 @@
+//! Leveled logging with per-connection context.
+//!
+//! Configured through environment variables:
+//! - `SCRABBLE_LOG`: one of `error`, `warn`, `info` (default), `debug`, `trace`
+//! - `SCRABBLE_LOG_FORMAT`: `text` (default) or `json`, for log shippers
+//!
+//! Every line carries the fields of the current connection's [`Context`], if any.
+//! Never pass racks, full tables, or other hidden game state as fields.
+
+use std::{
+    cell::RefCell,
+    fmt::{Arguments, Display},
+    io::Write,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use scrabble::table::TableId;
+use serde_json::{Map, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn parse(s: &str) -> Option<Level> {
+        use Level::*;
+        Some(match s.to_ascii_lowercase().as_str() {
+            "error" => Error,
+            "warn" => Warn,
+            "info" => Info,
+            "debug" => Debug,
+            "trace" => Trace,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        use Level::*;
+        match self {
+            Error => "error",
+            Warn => "warn",
+            Info => "info",
+            Debug => "debug",
+            Trace => "trace",
+        }
+    }
+}
+
+struct Config {
+    level: Level,
+    json: bool,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let level = std::env::var("SCRABBLE_LOG")
+            .ok()
+            .and_then(|s| Level::parse(&s))
+            .unwrap_or(Level::Info);
+        let json = std::env::var("SCRABBLE_LOG_FORMAT").is_ok_and(|s| s == "json");
+        Config { level, json }
+    })
+}
+
+/// Read the logging configuration from the environment. Logging works without calling this,
+/// but calling it early surfaces a bad configuration at startup rather than at the first log line.
+pub fn init() {
+    let config = config();
+    if let Some(s) = std::env::var("SCRABBLE_LOG")
+        .ok()
+        .filter(|s| Level::parse(s).is_none())
+    {
+        crate::warn!(
+            "Unknown SCRABBLE_LOG level {s:?}, using {}",
+            config.level.as_str()
+        );
+    }
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= config().level
+}
+
+/// Fields attached to every log line written from inside a connection's task.
+#[derive(Debug, Default)]
+pub struct Context {
+    pub connection_id: usize,
+    pub table: Option<TableId>,
+    pub name: Option<String>,
+}
+
+tokio::task_local! {
+    static CONTEXT: RefCell<Context>;
+}
+
+/// Run `f` with `ctx` as the logging context.
+pub async fn scope<F: std::future::Future>(ctx: Context, f: F) -> F::Output {
+    CONTEXT.scope(RefCell::new(ctx), f).await
+}
+
+/// Update the current task's logging context. Does nothing outside of [`scope`].
+pub fn update_context(f: impl FnOnce(&mut Context)) {
+    let _ = CONTEXT.try_with(|ctx| f(&mut ctx.borrow_mut()));
+}
+
+#[doc(hidden)]
+pub fn write(level: Level, fields: &[(&str, &dyn Display)], message: Arguments) {
+    let line = CONTEXT
+        .try_with(|ctx| format_line(level, Some(&ctx.borrow()), fields, message))
+        .unwrap_or_else(|_| format_line(level, None, fields, message));
+
+    if level <= Level::Warn {
+        let _ = writeln!(std::io::stderr(), "{line}");
+    } else {
+        let _ = writeln!(std::io::stdout(), "{line}");
+    }
+}
+
+fn format_line(
+    level: Level,
+    ctx: Option<&Context>,
+    fields: &[(&str, &dyn Display)],
+    message: Arguments,
+) -> String {
+    if config().json {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut obj = Map::new();
+        obj.insert("ts".into(), timestamp.into());
+        obj.insert("level".into(), level.as_str().into());
+        if let Some(ctx) = ctx {
+            obj.insert("connection_id".into(), ctx.connection_id.into());
+            if let Some(table) = ctx.table {
+                obj.insert("table".into(), table.into());
+            }
+            if let Some(name) = &ctx.name {
+                obj.insert("name".into(), name.as_str().into());
+            }
+        }
+        for (k, v) in fields {
+            obj.insert((*k).into(), v.to_string().into());
+        }
+        obj.insert("message".into(), message.to_string().into());
+        Value::Object(obj).to_string()
+    } else {
+        let mut line = format!("{:<5}", level.as_str().to_ascii_uppercase());
+        if let Some(ctx) = ctx {
+            line += &format!(" [{}", ctx.connection_id);
+            if let Some(table) = ctx.table {
+                line += &format!(" #{table}");
+            }
+            if let Some(name) = &ctx.name {
+                line += &format!(" {name}");
+            }
+            line += "]";
+        }
+        line += &format!(" {message}");
+        for (k, v) in fields {
+            line += &format!(" {k}={v}");
+        }
+        line
+    }
+}
+
+/// `log!(Level, key = value, ...; "format", args...)`
+#[macro_export]
+macro_rules! log {
+    ($level:ident $(, $key:ident = $val:expr)*; $($fmt:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::$level) {
+            $crate::log::write(
+                $crate::log::Level::$level,
+                &[$((stringify!($key), &$val as &dyn ::std::fmt::Display)),*],
+                format_args!($($fmt)*),
+            )
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($key:ident = $val:expr),+; $($fmt:tt)*) => { $crate::log!(Error, $($key = $val),+; $($fmt)*) };
+    ($($fmt:tt)*) => { $crate::log!(Error; $($fmt)*) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($key:ident = $val:expr),+; $($fmt:tt)*) => { $crate::log!(Warn, $($key = $val),+; $($fmt)*) };
+    ($($fmt:tt)*) => { $crate::log!(Warn; $($fmt)*) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($key:ident = $val:expr),+; $($fmt:tt)*) => { $crate::log!(Info, $($key = $val),+; $($fmt)*) };
+    ($($fmt:tt)*) => { $crate::log!(Info; $($fmt)*) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($key:ident = $val:expr),+; $($fmt:tt)*) => { $crate::log!(Debug, $($key = $val),+; $($fmt)*) };
+    ($($fmt:tt)*) => { $crate::log!(Debug; $($fmt)*) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($key:ident = $val:expr),+; $($fmt:tt)*) => { $crate::log!(Trace, $($key = $val),+; $($fmt)*) };
+    ($($fmt:tt)*) => { $crate::log!(Trace; $($fmt)*) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_levels() {
+        assert_eq!(Level::parse("DEBUG"), Some(Level::Debug));
+        assert_eq!(Level::parse("warn"), Some(Level::Warn));
+        assert_eq!(Level::parse("loud"), None);
+        assert!(Level::Error < Level::Trace);
+    }
+
+    #[test]
+    fn context_fields() {
+        let mut ctx = Context {
+            connection_id: 3,
+            ..Default::default()
+        };
+        let line = |ctx: &Context| format_line(Level::Info, Some(ctx), &[], format_args!("Hi"));
+        assert_eq!(line(&ctx), "INFO  [3] Hi");
+        ctx.table = Some(12);
+        ctx.name = Some("Alice".into());
+        assert_eq!(line(&ctx), "INFO  [3 #12 Alice] Hi");
+    }
+}
@@ -1,48 +1,116 @@
-mod game;
+mod log;
+mod metrics;
 mod version {
     include!(concat!(env!("OUT_DIR"), "/version.rs"));
 }
 
 use std::{
-    fmt::Display,
+    collections::HashMap,
     net::SocketAddr,
+    panic::AssertUnwindSafe,
     sync::{atomic::AtomicUsize, Arc, LazyLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        ConnectInfo, WebSocketUpgrade,
+        ConnectInfo, Path, Query, State, WebSocketUpgrade,
     },
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Json, Router,
+};
+use futures_util::FutureExt;
+use metrics::{TableCounts, METRICS};
+use scrabble::{
+    game::{
+        self,
+        analysis::GameAnalysis,
+        equity::{rank_moves, LEAVES},
+        Action, Board, Game, GameRecord, Move, Tile,
+    },
+    protocol::{
+        ClientMessage, ErrorCode, ServerError, ServerHello, ServerMessage, PROTOCOL_VERSION,
+    },
+    table::{
+        Dictionary, GameState, Table, TableId, TableListing, TableReply, WordCheck, WordQuery,
+        WordSearch,
+    },
 };
-use game::{Game, InvalidMove, Move};
 use serde::{Deserialize, Serialize};
 use tokio::{
     net::TcpListener,
-    sync::{broadcast, RwLock},
+    sync::{watch, RwLock, RwLockReadGuard},
     time::timeout,
 };
 use version::COMMIT_HASH;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    if std::env::args().any(|s| s == "--version" || s == "-v") {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|s| s == "--version" || s == "-v") {
         println!("{COMMIT_HASH}");
         return;
     }
 
-    println!("Server version: {COMMIT_HASH}");
+    // Offline tools print JSON instead of text when given --json
+    let json = args.iter().any(|s| s == "--json");
+    let res = match args
+        .iter()
+        .map(String::as_str)
+        .filter(|s| *s != "--json")
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["replay", path] => Some(replay(path)),
+        ["build-leaves", games, path] => Some(build_leaves(games, path)),
+        ["simulate", path] => Some(simulate(path, "100")),
+        ["simulate", path, iterations] => Some(simulate(path, iterations)),
+        ["analyze", path] => Some(analyze(path)),
+        ["endgame", path] => Some(endgame(path, "8")),
+        ["endgame", path, plies] => Some(endgame(path, plies)),
+        ["solve", path, rack] => Some(solve(path, rack, "10", json)),
+        ["solve", path, rack, count] => Some(solve(path, rack, count, json)),
+        ["check", words @ ..] if !words.is_empty() => Some(check_words(words, json)),
+        ["score", path, notation @ ..] if !notation.is_empty() => {
+            Some(score(path, &notation.join(" "), json))
+        }
+        _ => None,
+    };
+    if let Some(res) = res {
+        if let Err(e) = res {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    log::init();
+    info!("Server version: {COMMIT_HASH}");
 
+    let start = Instant::now();
     LazyLock::force(&game::solve::WORDLIST);
+    METRICS.wordlist_loaded(start.elapsed());
+    info!("Loaded word list in {:?}", start.elapsed());
 
-    println!("Scrabble server listening on port 2222");
+    info!("Scrabble server listening on port 2222");
     let g = Arc::new(GlobalState::new());
-    let app = Router::new().route("/", get(move |ws, ci| handle_connection(ws, ci, g.clone())));
+    tokio::spawn(evict_tables(g.clone()));
+    let app = Router::new()
+        .route("/", get(handle_connection))
+        .route("/tables", get(handle_list_tables))
+        .route("/tables/:id/record", get(handle_game_record))
+        .route("/tables/:id/analysis", get(handle_game_analysis))
+        .route(
+            "/tables/:id/archive/:number/record",
+            get(handle_archived_record),
+        )
+        .route("/words", get(handle_words))
+        .route("/metrics", get(handle_metrics))
+        .with_state(g);
     axum::serve(
         TcpListener::bind("0.0.0.0:2222").await.unwrap(),
         app.into_make_service_with_connect_info::<SocketAddr>(),
@@ -51,159 +119,864 @@ async fn main() {
     .unwrap();
 }
 
+/// Every minute, remove the tables that have been abandoned, see [`Table::is_abandoned`]
+async fn evict_tables(g: Global) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        let mut tables = g.tables.write().await;
+        let now = Instant::now();
+        let before = tables.len();
+        tables.retain(|&id, t| {
+            let abandoned = t.is_abandoned(now);
+            if abandoned {
+                info!(table = id; "Table evicted");
+            }
+            !abandoned
+        });
+        // Lobbies may have listed the evicted tables
+        if tables.len() < before {
+            g.send_update(&mut tables, None);
+        }
+    }
+}
+
 async fn handle_connection(
     ws: WebSocketUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    g: Global,
+    State(g): State<Global>,
 ) -> impl IntoResponse {
     ws.on_upgrade(move |sock| Connection::handle_connection(sock, g, addr))
 }
 
-tokio::task_local! {
-    // Connection ID
-    static CONNECTION_ID: usize;
+async fn handle_metrics(State(g): State<Global>) -> String {
+    let tables = g.tables.read().await;
+    let count = |state| tables.values().filter(|t| t.state == state).count();
+    METRICS.render(TableCounts {
+        setup: count(GameState::Setup),
+        running: count(GameState::Running),
+        finished: count(GameState::Finished),
+    })
+}
+
+async fn handle_list_tables(State(g): State<Global>) -> Json<Vec<TableListing>> {
+    Json(lobby_listings(&*g.tables.read().await))
+}
+
+/// A player's seat, for requests only a table's own players may make: `?name=Alice&token=...`
+#[derive(Deserialize)]
+struct SeatParams {
+    name: Option<String>,
+    token: Option<String>,
+}
+
+/// Records hold the seed and every rack, which players only see their own share of while
+/// playing, so nobody may see one before the game is over, and only the table's own players
+/// if the table is private.
+fn ensure_may_see_record(
+    table: &Table,
+    finished: bool,
+    seat: &SeatParams,
+) -> Result<(), StatusCode> {
+    let player = match (&seat.name, &seat.token) {
+        (Some(name), Some(token)) => table.claim_seat(name, token).is_ok(),
+        _ => false,
+    };
+    if finished && (player || !table.is_private()) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+async fn handle_game_record(
+    Path(id): Path<TableId>,
+    Query(seat): Query<SeatParams>,
+    State(g): State<Global>,
+) -> Result<Json<GameRecord>, StatusCode> {
+    let tables = g.tables.read().await;
+    let table = tables.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    ensure_may_see_record(table, table.state == GameState::Finished, &seat)?;
+    Ok(Json(table.game.record()))
+}
+
+/// The record of a finished game from a table's archive
+async fn handle_archived_record(
+    Path((id, number)): Path<(TableId, usize)>,
+    Query(seat): Query<SeatParams>,
+    State(g): State<Global>,
+) -> Result<Json<GameRecord>, StatusCode> {
+    let tables = g.tables.read().await;
+    let table = tables.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    ensure_may_see_record(table, true, &seat)?;
+    let archived = table
+        .archive
+        .iter()
+        .find(|a| a.number == number)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(archived.record.clone()))
+}
+
+/// Compare every move played at a table with the best move available. Analysis runs off the async thread.
+async fn handle_game_analysis(
+    Path(id): Path<TableId>,
+    Query(seat): Query<SeatParams>,
+    State(g): State<Global>,
+) -> Result<Json<GameAnalysis>, StatusCode> {
+    let record = {
+        let tables = g.tables.read().await;
+        let table = tables.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+        ensure_may_see_record(table, table.state == GameState::Finished, &seat)?;
+        table.game.record()
+    };
+    let analysis = tokio::task::spawn_blocking(move || Game::analyze(&record))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(analysis))
+}
+
+#[derive(Deserialize)]
+struct WordsParams {
+    table: Option<TableId>,
+    check: Option<String>,
+    letters: Option<String>,
+    pattern: Option<String>,
+}
+
+/// Dictionary lookups for players away from the table: `/words?check=QI`, `/words?letters=AEINRST?`,
+/// or `/words?pattern=C?T*`. Uses the default dictionary, or with `table=` that table's, following
+/// its lookup rule for the seat given (see [`SeatParams`]) just as it does over the websocket.
+/// Searches run off the async thread.
+async fn handle_words(
+    Query(params): Query<WordsParams>,
+    Query(seat): Query<SeatParams>,
+    State(g): State<Global>,
+) -> Result<Response, (StatusCode, String)> {
+    let dictionary = match params.table {
+        Some(id) => {
+            let tables = g.tables.read().await;
+            let table = tables
+                .get(&id)
+                .ok_or((StatusCode::NOT_FOUND, "No such table".into()))?;
+            let name = match (&seat.name, &seat.token) {
+                (Some(name), Some(token)) => {
+                    table
+                        .claim_seat(name, token)
+                        .map_err(|e| (StatusCode::FORBIDDEN, e.to_string()))?;
+                    Some(name.as_str())
+                }
+                _ => None,
+            };
+            table
+                .ensure_lookups_allowed(name)
+                .map_err(|e| (StatusCode::FORBIDDEN, e.to_string()))?;
+            table.dictionary
+        }
+        None => Dictionary::default(),
+    };
+    let query = match params {
+        WordsParams {
+            check: Some(word), ..
+        } => {
+            let valid = dictionary.is_word(&word);
+            return Ok(Json(WordCheck { word, valid }).into_response());
+        }
+        WordsParams {
+            letters: Some(letters),
+            ..
+        } => WordQuery::Letters(letters),
+        WordsParams {
+            pattern: Some(pattern),
+            ..
+        } => WordQuery::Pattern(pattern),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Expected check, letters or pattern".into(),
+            ))
+        }
+    };
+    let search = query.clone();
+    let words = tokio::task::spawn_blocking(move || dictionary.search(&search))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(WordSearch { query, words }).into_response())
+}
+
+fn load_record(path: &str) -> Result<(GameRecord, Game)> {
+    let record: GameRecord = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let game = Game::replay(&record).map_err(|e| match e.action {
+        Some(i) => anyhow!(
+            "Action {i} ({:?}) failed: {}",
+            record.actions[i],
+            e.explanation
+        ),
+        None => anyhow!("{}", e.explanation),
+    })?;
+    Ok((record, game))
+}
+
+/// Debugging tool: play back a game record saved from `/tables/:id/record` and print where it ends up
+fn replay(path: &str) -> Result<()> {
+    let (record, game) = load_record(path)?;
+
+    println!(
+        "Replayed {} actions with seed {}",
+        record.actions.len(),
+        record.seed
+    );
+    for p in game.players() {
+        let rack: String = p.tiles().iter().map(ToString::to_string).collect();
+        println!("{}: {} points, rack {rack}", p.name(), p.score());
+    }
+    print!("{:#}", game.board());
+    println!("{} tiles in the bag", game.tiles_in_bag());
+    if game.is_finished() {
+        println!("The game is over");
+    } else {
+        println!("It's {}'s turn", game.current_player().name());
+    }
+    Ok(())
+}
+
+/// Rank the moves for the player to move at the end of a game record by simulation
+fn simulate(path: &str, iterations: &str) -> Result<()> {
+    let (_, game) = load_record(path)?;
+    ensure!(!game.is_finished(), "The game is over");
+    let config = game::solve::SimConfig {
+        iterations: iterations.parse()?,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let results = game::solve::simulate(&game, &config);
+    println!(
+        "{} moves for {}, {} iterations in {:?}",
+        results.len(),
+        game.current_player().name(),
+        config.iterations,
+        start.elapsed()
+    );
+    println!(
+        "{:<20} {:>5} {:>7} {:>7}",
+        "Move", "Score", "Equity", "Spread"
+    );
+    for r in results {
+        let m = r.m.to_string();
+        println!("{m:<20} {:>5} {:>7.2} {:>7.2}", r.score, r.equity, r.spread);
+    }
+    Ok(())
+}
+
+/// Read a board grid from a file, or from stdin if `path` is `-`
+fn read_board(path: &str) -> Result<Board> {
+    let grid = match path {
+        "-" => std::io::read_to_string(std::io::stdin())?,
+        path => std::fs::read_to_string(path)?,
+    };
+    grid.parse().map_err(|e: String| anyhow!(e))
+}
+
+#[derive(Serialize)]
+struct SolvedMove {
+    #[serde(rename = "move")]
+    notation: String,
+    score: u32,
+    equity: f64,
+    leave: String,
+}
+
+/// Print the best moves for `rack` on the board in `path`, by equity
+fn solve(path: &str, rack: &str, count: &str, json: bool) -> Result<()> {
+    let board = read_board(path)?;
+    let rack = Tile::parse_rack(rack).map_err(|e| anyhow!(e))?;
+    let moves: Vec<SolvedMove> = rank_moves(&board, &rack, &LEAVES)
+        .into_iter()
+        .take(count.parse()?)
+        .map(|r| SolvedMove {
+            notation: r.m.to_string(),
+            score: r.score,
+            equity: r.equity,
+            leave: r.leave.iter().map(ToString::to_string).collect(),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&moves)?);
+        return Ok(());
+    }
+    if moves.is_empty() {
+        println!("No moves");
+    }
+    for m in moves {
+        println!(
+            "{:<20} {:>4} {:>7.2}  {}",
+            m.notation, m.score, m.equity, m.leave
+        );
+    }
+    Ok(())
+}
+
+/// Print whether each word is in the default dictionary
+fn check_words(words: &[&str], json: bool) -> Result<()> {
+    let dictionary = Dictionary::default();
+    let checks: Vec<WordCheck> = words
+        .iter()
+        .map(|w| WordCheck {
+            word: w.to_uppercase(),
+            valid: dictionary.is_word(w),
+        })
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        for c in &checks {
+            let valid = if c.valid { "valid" } else { "invalid" };
+            println!("{} {valid}", c.word);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct MoveScore {
+    #[serde(rename = "move")]
+    notation: String,
+    valid: bool,
+    score: u32,
+    words: Vec<(String, u32)>,
+    explanation: Option<String>,
 }
 
-macro_rules! log {
-    ($($x:tt)*) => {
-        CONNECTION_ID.with(|cid| println!("[{}] {}", cid, format_args!($($x)*)))
+/// Print what a move scores on the board in `path`, word by word. Fails if the move isn't valid.
+fn score(path: &str, notation: &str, json: bool) -> Result<()> {
+    let mut board = read_board(path)?;
+    let m = Move::parse(&board, notation).map_err(|e| anyhow!(e))?;
+    let result = match board.play_move(&m) {
+        Ok(played) => MoveScore {
+            notation: m.to_string(),
+            valid: true,
+            score: played.value(),
+            words: played
+                .word_values()
+                .iter()
+                .map(|(w, v)| (w.to_uppercase(), *v))
+                .collect(),
+            explanation: None,
+        },
+        Err(im) => MoveScore {
+            notation: m.to_string(),
+            valid: false,
+            score: 0,
+            words: vec![],
+            explanation: Some(im.explanation),
+        },
     };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else if result.valid {
+        for (word, value) in &result.words {
+            println!("{word} {value}");
+        }
+        println!("{} scores {}", result.notation, result.score);
+    }
+    match result.explanation {
+        Some(explanation) => bail!("{}: {explanation}", result.notation),
+        None => Ok(()),
+    }
+}
+
+/// Print how every move in a saved game compares with the best move available
+fn analyze(path: &str) -> Result<()> {
+    let (record, _) = load_record(path)?;
+    let analysis = Game::analyze(&record).map_err(|e| anyhow!("{}", e.explanation))?;
+    println!(
+        "{:<12} {:<20} {:>5}  {:<20} {:>5} {:>5}",
+        "Player", "Played", "Score", "Best", "Score", "Lost"
+    );
+    for m in &analysis.moves {
+        println!(
+            "{:<12} {:<20} {:>5}  {:<20} {:>5} {:>5}",
+            m.player,
+            m.played.to_string(),
+            m.score,
+            m.best.to_string(),
+            m.best_score,
+            m.difference()
+        );
+        for (bingo, score) in &m.missed_bingos {
+            println!("{:<12} missed bingo {bingo} for {score}", "");
+        }
+    }
+    println!();
+    for p in &analysis.players {
+        println!(
+            "{}: {}/{} points ({:.1}%), best move {} of {} times, {} missed bingos",
+            p.name,
+            p.points,
+            p.best_points,
+            p.accuracy * 100.0,
+            p.best_moves,
+            p.moves,
+            p.missed_bingos
+        );
+    }
+    Ok(())
+}
+
+/// Solve the endgame of a saved game whose bag is empty
+fn endgame(path: &str, plies: &str) -> Result<()> {
+    let (_, game) = load_record(path)?;
+    let start = Instant::now();
+    let solution = game
+        .solve_endgame(plies.parse()?)
+        .ok_or_else(|| anyhow!("Not an endgame: the bag must be empty with two players left"))?;
+    print!("{:#}", game.board());
+    println!(
+        "Best line for {}, solved in {:?}",
+        game.current_player().name(),
+        start.elapsed()
+    );
+    for action in &solution.line {
+        match action {
+            Action::PlayMove(m) => println!("  {m}"),
+            _ => println!("  exchange"),
+        }
+    }
+    println!("Spread: {:+}", solution.spread);
+    Ok(())
+}
+
+/// Improve the leave values in `leaves.txt` by self-play, and write them to `path`
+fn build_leaves(games: &str, path: &str) -> Result<()> {
+    let games: u64 = games.parse()?;
+    let start = Instant::now();
+    let leaves = game::equity::build_leaves(&game::equity::LEAVES, games, 0);
+    let header =
+        "# Rack leave values in points, built by `server build-leaves`. See src/game/equity.rs.\n";
+    std::fs::write(path, header.to_string() + &leaves.to_file_string())?;
+    println!("Played {games} games in {:?}", start.elapsed());
+    Ok(())
 }
 
 struct Connection {
     ws: WebSocket,
     g: Global,
     addr: SocketAddr,
+    table: Option<TableId>,
     name: Option<String>,
-    update_recv: broadcast::Receiver<()>,
+    updates: watch::Receiver<u64>,
+    /// The table and version of it that the client was last sent, set by [`Connection::send_view`]
+    sent_view: Option<(TableId, u64)>,
+    /// Whether the client has sent a compatible `Hello`, which it must before anything else
+    greeted: bool,
+    /// Held while at a table, see [`Table::is_abandoned`]
+    presence: Option<Arc<()>>,
 }
 
 impl Connection {
     async fn handle_connection(ws: WebSocket, g: Global, addr: SocketAddr) {
-        CONNECTION_ID
-            .scope(count(), async move {
-                let update_recv = g.update_send.subscribe();
-                let mut handler = Connection {
-                    ws,
-                    g,
-                    addr,
-                    name: None,
-                    update_recv,
-                };
+        let ctx = log::Context {
+            connection_id: count(),
+            ..Default::default()
+        };
+        log::scope(ctx, async move {
+            METRICS.connection_opened();
+            let updates = g.version.subscribe();
+            let mut handler = Connection {
+                ws,
+                g,
+                addr,
+                table: None,
+                name: None,
+                updates,
+                sent_view: None,
+                greeted: false,
+                presence: None,
+            };
 
-                if let Err(e) = handler.main_loop().await {
-                    log!("Error: {e}");
-                }
-            })
-            .await
+            if let Err(e) = handler.main_loop().await {
+                info!("Connection closed: {e}");
+            }
+            METRICS.connection_closed();
+        })
+        .await
     }
 
     async fn main_loop(&mut self) -> Result<()> {
-        log!("Connection from {}", self.addr);
+        info!(addr = self.addr; "Connection opened");
 
         loop {
             tokio::select! {
-                msg = self.ws.recv_message() => {
-                    self.handle_message(msg?).await?;
+                json = self.ws.recv_message() => {
+                    let res = match serde_json::from_str::<ClientMessage>(&json?) {
+                        Ok(msg) => {
+                            debug!(msg_type = msg.kind(); "Message recv");
+                            METRICS.message_received(msg.kind());
+                            self.handle_message_isolated(msg).await
+                        }
+                        Err(e) => {
+                            let message = format!("Invalid message: {e}");
+                            Err(ServerError::new(ErrorCode::BadMessage, message).into())
+                        }
+                    };
+                    if let Err(e) = res {
+                        self.send_error(e).await?;
+                    }
                 }
 
-                recv_res = self.update_recv.recv() => {
-                    if recv_res == Err(broadcast::error::RecvError::Closed) {
-                        unreachable!("update broadcast sender was dropped");
+                changed = self.updates.changed() => {
+                    changed?;
+                    if !self.greeted {
+                        continue;
                     }
 
-                    // Pretend that being notified of an update from another task
-                    // is actually receiving an update request from the client
-                    self.handle_message(ClientMessage::UpdateMe).await?;
+                    let stale = {
+                        let tables = self.g.tables.read().await;
+                        needs_view(self.table, self.sent_view, &tables)
+                    };
+
+                    // Pretend that being notified of an update from another task is actually
+                    // receiving an update request from the client. However many updates were
+                    // made since the last one we saw, the view we send is as of the latest.
+                    if stale {
+                        if let Err(e) = self.handle_message_isolated(ClientMessage::UpdateMe).await {
+                            self.send_error(e).await?;
+                        }
+                    }
                 }
             }
         }
     }
 
+    /// Handle a message, turning a panic into an error so that a bug only ends this connection
+    async fn handle_message_isolated(&mut self, msg: ClientMessage) -> Result<()> {
+        let kind = msg.kind();
+        match AssertUnwindSafe(self.handle_message(msg))
+            .catch_unwind()
+            .await
+        {
+            Ok(res) => res,
+            Err(_) => {
+                error!(msg_type = kind; "Panic while handling message");
+                let message = "Something went wrong on the server";
+                Err(ServerError::new(ErrorCode::Internal, message).into())
+            }
+        }
+    }
+
+    /// Tell the client why its request failed. Errors that aren't a [`ServerError`] are sent as
+    /// [`ErrorCode::Refused`]. Returns an error to close the connection if the connection itself
+    /// failed or the error can't be recovered from.
+    async fn send_error(&mut self, e: anyhow::Error) -> Result<()> {
+        if e.is::<axum::Error>() {
+            return Err(e);
+        }
+        let error = match e.downcast::<ServerError>() {
+            Ok(error) => error,
+            Err(e) => ServerError::new(ErrorCode::Refused, e.to_string()),
+        };
+        debug!(code = error.code; "Request failed: {}", error.message);
+        METRICS.error_sent(error.code);
+        self.ws.send_msg(ServerMessage::Error(&error)).await?;
+        ensure!(error.code.is_recoverable(), error);
+        Ok(())
+    }
+
     async fn handle_message(&mut self, msg: ClientMessage) -> Result<()> {
-        macro_rules! table {
+        // Locked through a clone, so that `self` can still be borrowed while the tables are
+        let g = self.g.clone();
+        macro_rules! tables {
             () => {{
-                let timeout = timeout(Duration::from_secs(10), self.g.table.read());
-                &*timeout.await.expect("Rwlock read timeout")
+                let timeout = timeout(Duration::from_secs(10), g.tables.read());
+                let busy = || ServerError::new(ErrorCode::Busy, "The server is busy, try again");
+                timeout.await.map_err(|_| busy())?
             }};
             (mut) => {{
-                let timeout = timeout(Duration::from_secs(10), self.g.table.write());
-                &mut *timeout.await.expect("Rwlock write timeout")
+                let timeout = timeout(Duration::from_secs(10), g.tables.write());
+                let busy = || ServerError::new(ErrorCode::Busy, "The server is busy, try again");
+                timeout.await.map_err(|_| busy())?
             }};
         }
 
+        macro_rules! table {
+            ($tables:ident) => {
+                self.table
+                    .and_then(|id| $tables.get_mut(&id))
+                    .ok_or_else(|| ServerError::new(ErrorCode::NotAtTable, "Not at a table"))?
+            };
+        }
+
+        ensure!(
+            self.greeted || matches!(msg, ClientMessage::Hello(_)),
+            ServerError::new(
+                ErrorCode::Incompatible,
+                "This client is out of date and needs updating"
+            )
+        );
+
         let mut update_everyone = true;
         match msg {
+            ClientMessage::Hello(hello) => {
+                update_everyone = false;
+                ensure!(
+                    hello.protocol == PROTOCOL_VERSION,
+                    ServerError::new(
+                        ErrorCode::Incompatible,
+                        format!(
+                            "This client speaks version {} of the protocol but the server speaks \
+                             version {PROTOCOL_VERSION}, so it needs updating",
+                            hello.protocol
+                        )
+                    )
+                );
+                let build = hello.build.as_deref().unwrap_or("unknown");
+                info!(protocol = hello.protocol, build = build; "Client said hello");
+                self.greeted = true;
+                let hello = ServerHello {
+                    protocol: PROTOCOL_VERSION,
+                    build: COMMIT_HASH.into(),
+                };
+                self.ws.send_msg(ServerMessage::Hello(&hello)).await?;
+            }
             ClientMessage::UpdateMe => {
-                let table = table!();
+                let tables = tables!();
                 update_everyone = false;
-                self.ws.send_msg(ServerMessage::Table(table)).await?;
+                // The host may have removed us from the table, or the table may have been evicted
+                let table = self.table.and_then(|id| tables.get(&id));
+                match (table, &self.name) {
+                    (None, _) if self.table.is_some() => self.leave_table(),
+                    (Some(table), Some(name)) if !table.game.has_player(name) => {
+                        self.name = None;
+                        log::update_context(|ctx| ctx.name = None);
+                    }
+                    _ => {}
+                }
+                self.send_view(tables).await?;
             }
-            ClientMessage::StartGame => {
-                let table = table!(mut);
-                if table.state != GameState::Setup {
-                    // If two people are in the game start prompt, this can lead to double starts.
-                    // Just ignore secondary start messages.
-                    log!("Ignored StartGame message");
-                    return Ok(());
+            ClientMessage::CreateTable {
+                name,
+                rules,
+                private,
+            } => {
+                ensure!(
+                    self.table.is_none(),
+                    ServerError::new(ErrorCode::AlreadyAtTable, "Already at a table")
+                );
+                let mut tables = tables!(mut);
+                let table = Table::new(name.clone(), rules, private)?;
+                info!(table = table.id; "Table created");
+                self.join_table(&table);
+                let seat = table.seat(&name);
+                self.name = Some(name);
+                log::update_context(|ctx| ctx.name.clone_from(&self.name));
+                tables.insert(table.id, table);
+                drop(tables);
+                if let Some(seat) = seat {
+                    self.ws.send_msg(ServerMessage::Seat(&seat)).await?;
                 }
-                ensure!(table.game.ready_to_play(), "Game is not ready to play");
-                table.state = GameState::Running;
-                table.game.start_game();
             }
-            ClientMessage::JoinWithName(name) => {
+            ClientMessage::JoinTable(id) => {
+                ensure!(
+                    self.table.is_none(),
+                    ServerError::new(ErrorCode::AlreadyAtTable, "Already at a table")
+                );
+                let tables = tables!();
+                let table = tables
+                    .get(&id)
+                    .ok_or_else(|| ServerError::new(ErrorCode::NoSuchTable, "No such table"))?;
+                ensure!(
+                    !table.is_private(),
+                    ServerError::new(ErrorCode::NoSuchTable, "That table needs an invite code")
+                );
+                update_everyone = false;
+                self.join_table(table);
+                self.send_view(tables).await?;
+            }
+            ClientMessage::JoinWithInvite(code) => {
                 ensure!(
-                    self.name.is_none() || self.name.as_ref().unwrap() == &name,
+                    self.table.is_none(),
+                    ServerError::new(ErrorCode::AlreadyAtTable, "Already at a table")
+                );
+                let tables = tables!();
+                let code = code.trim().to_ascii_uppercase();
+                let table = tables
+                    .values()
+                    .find(|t| t.invite_code.as_ref() == Some(&code))
+                    .ok_or_else(|| {
+                        ServerError::new(ErrorCode::NoSuchTable, "Invalid invite code")
+                    })?;
+                update_everyone = false;
+                self.join_table(table);
+                self.send_view(tables).await?;
+            }
+            ClientMessage::LeaveTable => {
+                let tables = tables!();
+                update_everyone = false;
+                self.leave_table();
+                self.send_view(tables).await?;
+            }
+            ClientMessage::ClaimSeat { name, token } => {
+                ensure!(
+                    self.name.as_ref().is_none_or(|n| n == &name),
                     "Player is already in the game but tried to set a new name"
                 );
-                let table = table!(mut);
-                if table.game.has_player(&name) {
-                    update_everyone = false;
-                    self.name = Some(name);
-                } else {
-                    match table.state {
-                        GameState::Setup => {
-                            ensure!(table.game.players().len() <= 4, "Game already full");
-                            table.game.add_player(name.clone());
-                            self.name = Some(name);
-                        }
-                        GameState::Running => {
-                            ensure!(table.game.has_player(&name), "No player with given name");
-                            self.name = Some(name);
-                        }
+                let mut tables = tables!(mut);
+                let table = table!(tables);
+                table.claim_seat(&name, &token)?;
+                update_everyone = false;
+                self.name = Some(name);
+                log::update_context(|ctx| ctx.name.clone_from(&self.name));
+            }
+            ClientMessage::CheckWord(word) if self.table.is_none() => {
+                update_everyone = false;
+                let valid = Dictionary::default().is_word(&word);
+                let check = WordCheck { word, valid };
+                self.ws.send_msg(ServerMessage::WordCheck(&check)).await?;
+            }
+            ClientMessage::SearchWords(query) if self.table.is_none() => {
+                update_everyone = false;
+                self.search_words(Dictionary::default(), query).await?;
+            }
+            // Everything else is up to the table, see `Table::handle`
+            msg => {
+                let event = match &msg {
+                    ClientMessage::KickPlayer(name) => {
+                        Some(format!("Player removed from game: {name}"))
                     }
+                    ClientMessage::ForfeitPlayer(name) => Some(format!("Player forfeited: {name}")),
+                    ClientMessage::ResetTable => Some("Table reset".to_owned()),
+                    _ => None,
+                };
+                let play = matches!(msg, ClientMessage::PlayMove(_));
+                let mut tables = tables!(mut);
+                let table = table!(tables);
+                let start = Instant::now();
+                let reply = table.handle(self.name.as_deref(), msg)?;
+                if play {
+                    METRICS.observe_validation(start.elapsed());
                 }
-            }
-            ClientMessage::PlayMove(m) => {
-                let table = table!(mut);
-                ensure!(table.state == GameState::Running, "Game is not running");
-                ensure!(self.name.is_some(), "Not in the game");
-                let name = self.name.as_ref().unwrap();
-                ensure!(table.game.is_players_turn(name), "It's not your turn");
-                match table.game.play_move(&m) {
-                    Ok(()) => (),
-                    Err(im) => {
-                        update_everyone = false;
+                drop(tables);
+
+                update_everyone = reply.changed();
+                if let Some(event) = event {
+                    info!("{event}");
+                }
+                match reply {
+                    TableReply::Changed | TableReply::Unchanged => {}
+                    TableReply::Seated(seat) => {
+                        info!("Player joined game: {}", seat.name);
+                        self.name = Some(seat.name.clone());
+                        log::update_context(|ctx| ctx.name.clone_from(&self.name));
+                        self.ws.send_msg(ServerMessage::Seat(&seat)).await?;
+                    }
+                    TableReply::InvalidMove(im) => {
+                        debug!(explanation = im.explanation; "Invalid move");
+                        METRICS.invalid_move(&im.explanation);
                         self.ws.send_msg(ServerMessage::InvalidMove(&im)).await?;
                     }
+                    TableReply::WordCheck(check) => {
+                        self.ws.send_msg(ServerMessage::WordCheck(&check)).await?;
+                    }
+                    TableReply::Search(dictionary, query) => {
+                        self.search_words(dictionary, query).await?;
+                    }
+                    TableReply::UnseenTiles(unseen) => {
+                        self.ws
+                            .send_msg(ServerMessage::UnseenTiles(&unseen))
+                            .await?;
+                    }
+                    TableReply::TileOdds(odds) => {
+                        self.ws.send_msg(ServerMessage::TileOdds(&odds)).await?;
+                    }
                 }
             }
-            ClientMessage::ExchangeTiles => {
-                let table = table!(mut);
-                ensure!(table.state == GameState::Running, "Game is not running");
-                ensure!(self.name.is_some(), "Not in the game");
-                let name = self.name.as_ref().unwrap();
-                ensure!(table.game.is_players_turn(name), "It's not your turn");
-                table.game.exchange_tiles();
-            }
         }
 
+        // The change has been made, so it can't fail now for being busy
         if update_everyone {
-            self.g.send_update();
+            let mut tables = self.g.tables.write().await;
+            self.g.send_update(&mut tables, self.table);
         }
 
         Ok(())
     }
+
+    /// Watch a table, before taking a seat at it
+    fn join_table(&mut self, table: &Table) {
+        self.table = Some(table.id);
+        self.presence = Some(table.presence());
+        log::update_context(|ctx| ctx.table = self.table);
+    }
+
+    fn leave_table(&mut self) {
+        self.table = None;
+        self.presence = None;
+        self.name = None;
+        log::update_context(|ctx| {
+            ctx.table = None;
+            ctx.name = None;
+        });
+    }
+
+    /// Search a dictionary off the async thread, since searches can take a while, and send the results
+    async fn search_words(&mut self, dictionary: Dictionary, query: WordQuery) -> Result<()> {
+        let search = query.clone();
+        let words = tokio::task::spawn_blocking(move || dictionary.search(&search)).await??;
+        let search = WordSearch { query, words };
+        self.ws.send_msg(ServerMessage::WordSearch(&search)).await?;
+        Ok(())
+    }
+
+    /// Send the client its current view: its table, or the lobby if it isn't at one, and remember
+    /// which version of the table that was. The tables are unlocked before sending, so a client
+    /// that's slow to read can't hold up everyone else.
+    async fn send_view(&mut self, tables: RwLockReadGuard<'_, Tables>) -> Result<()> {
+        self.sent_view = view_version(self.table, &tables);
+        let (lobby, view);
+        let msg = match self.table.and_then(|id| tables.get(&id)) {
+            Some(table) => {
+                view = table.view_for(self.name.as_deref());
+                ServerMessage::Table(&view)
+            }
+            None => {
+                lobby = lobby_listings(&tables);
+                ServerMessage::Lobby(&lobby)
+            }
+        };
+        trace!(msg = msg; "Message send");
+        let json = serde_json::to_string(&msg)?;
+        drop(tables);
+        self.ws.send(Message::Text(json)).await?;
+        Ok(())
+    }
+}
+
+/// The table and version of it that a client at `table` is shown, or `None` in the lobby
+fn view_version(table: Option<TableId>, tables: &Tables) -> Option<(TableId, u64)> {
+    table
+        .and_then(|id| tables.get(&id))
+        .map(|t| (t.id, t.version))
+}
+
+/// Whether a client that was last sent `sent_view` needs a new view after an update. A client at a
+/// table that hasn't changed since doesn't, while one in the lobby always does, since any table
+/// may have changed what the lobby lists.
+fn needs_view(table: Option<TableId>, sent_view: Option<(TableId, u64)>, tables: &Tables) -> bool {
+    table.is_none() || view_version(table, tables) != sent_view
+}
+
+/// Public tables that can still be joined, oldest first
+fn lobby_listings(tables: &Tables) -> Vec<TableListing> {
+    let mut listings: Vec<_> = tables
+        .values()
+        .filter(|t| t.state == GameState::Setup && !t.is_private())
+        .map(Table::listing)
+        .collect();
+    listings.sort_by_key(|l| l.id);
+    listings
 }
 
 fn count() -> usize {
@@ -213,65 +986,41 @@ fn count() -> usize {
 
 type Global = Arc<GlobalState>;
 
+type Tables = HashMap<TableId, Table>;
+
 struct GlobalState {
-    table: RwLock<Table>,
-    update_send: broadcast::Sender<()>,
+    tables: RwLock<Tables>,
+    /// Number of updates made to the tables so far. Connections wait for it to change and then
+    /// send their client a fresh view, so a connection that falls behind skips straight to the
+    /// latest state rather than missing some of it.
+    version: watch::Sender<u64>,
 }
 
 impl GlobalState {
     fn new() -> Self {
         Self {
-            table: RwLock::new(Table::new()),
-            update_send: broadcast::channel(1).0,
-        }
-    }
-
-    fn send_update(&self) {
-        if let Err(broadcast::error::SendError(())) = self.update_send.send(()) {
-            unreachable!("there are no tasks receiving updates, but a task sent one out")
+            tables: RwLock::new(HashMap::new()),
+            version: watch::channel(0).0,
         }
     }
-}
 
-#[derive(Debug, Clone, Serialize)]
-struct Table {
-    game: Game,
-    state: GameState,
-}
-
-impl Table {
-    fn new() -> Self {
-        Table {
-            game: Game::new(),
-            state: GameState::Setup,
+    /// Notify every connection of a change to the tables, marking the table `changed` with the new version.
+    /// Takes the tables locked for writing, so that versions follow the order changes were made in.
+    fn send_update(&self, tables: &mut Tables, changed: Option<TableId>) -> u64 {
+        self.version.send_modify(|v| *v += 1);
+        let version = *self.version.borrow();
+        if let Some(table) = changed.and_then(|id| tables.get_mut(&id)) {
+            table.version = version;
+            table.touch();
         }
+        version
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
-enum GameState {
-    Setup,
-    Running,
-}
-
-#[derive(Debug, Clone, Serialize)]
-enum ServerMessage<'a> {
-    Table(&'a Table),
-    InvalidMove(&'a InvalidMove),
-}
-
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
-enum ClientMessage {
-    UpdateMe,
-    StartGame,
-    JoinWithName(String),
-    PlayMove(Move),
-    ExchangeTiles,
-}
-
 #[extend::ext]
 impl WebSocket {
-    async fn recv_message(&mut self) -> Result<ClientMessage> {
+    /// The text of the next message from the client, which should be a [`ClientMessage`] as JSON
+    async fn recv_message(&mut self) -> Result<String> {
         loop {
             tokio::select! {
                 msg_res = self.recv() => {
@@ -281,11 +1030,7 @@ impl WebSocket {
                         None => bail!("Client already disconnected"),
                     };
                     match msg {
-                        Message::Text(json) => {
-                            let msg = serde_json::from_str(&json)?;
-                            log!("Message recv: {msg:?}");
-                            break Ok(msg);
-                        }
+                        Message::Text(json) => break Ok(json),
                         Message::Close(frame) => bail!("Close frame received: {frame:?}"),
                         Message::Binary(_) => bail!("Received binary message"),
                         Message::Ping(data) => self.pong(data).await?,
@@ -301,7 +1046,7 @@ impl WebSocket {
     }
 
     async fn send_msg(&mut self, msg: ServerMessage<'_>) -> Result<()> {
-        log!("Message send: {msg}");
+        trace!(msg = msg; "Message send");
         self.send(Message::Text(serde_json::to_string(&msg)?))
             .await?;
         Ok(())
@@ -313,13 +1058,94 @@ impl WebSocket {
     }
 }
 
-impl Display for ServerMessage<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ServerMessage::Table(t) => write!(f, "Table {{ state: {:?}, .. }}", t.state),
-            ServerMessage::InvalidMove(im) => {
-                write!(f, "InvalidMove {{ explanation: {}, .. }}", im.explanation)
-            }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scrabble::table::Rules;
+
+    fn global_with_table() -> (Global, TableId) {
+        let g = Arc::new(GlobalState::new());
+        let table = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        let id = table.id;
+        g.tables.try_write().unwrap().insert(id, table);
+        (g, id)
+    }
+
+    #[test]
+    fn table_versions() {
+        let (g, id) = global_with_table();
+        let other = Table::new("Bob".into(), Rules::default(), false).unwrap();
+        let other_id = other.id;
+        let mut tables = g.tables.try_write().unwrap();
+        tables.insert(other_id, other);
+
+        assert_eq!(g.send_update(&mut tables, Some(id)), 1);
+        assert_eq!(g.send_update(&mut tables, Some(other_id)), 2);
+        assert_eq!(g.send_update(&mut tables, None), 3);
+        assert_eq!(tables[&id].version, 1);
+        assert_eq!(tables[&other_id].version, 2);
+        assert_eq!(*g.version.borrow(), 3);
+    }
+
+    #[test]
+    fn views_are_only_resent_when_stale() {
+        let (g, id) = global_with_table();
+        let mut tables = g.tables.try_write().unwrap();
+        assert!(needs_view(None, None, &tables));
+        assert!(needs_view(Some(id), None, &tables));
+
+        let sent = view_version(Some(id), &tables);
+        assert!(!needs_view(Some(id), sent, &tables));
+        // Another table changing doesn't change this one
+        g.send_update(&mut tables, None);
+        assert!(!needs_view(Some(id), sent, &tables));
+        g.send_update(&mut tables, Some(id));
+        assert!(needs_view(Some(id), sent, &tables));
+
+        let sent = view_version(Some(id), &tables);
+        assert!(!needs_view(Some(id), sent, &tables));
+        // An evicted table needs the client told, so that it goes back to the lobby
+        tables.remove(&id);
+        assert!(needs_view(Some(id), sent, &tables));
+    }
+
+    #[tokio::test]
+    async fn slow_consumers_converge() {
+        const UPDATES: u64 = 100;
+        let (g, id) = global_with_table();
+
+        // Connections that take a while to handle each update, like clients on slow networks
+        let consumers: Vec<_> = (1..=3)
+            .map(|delay| {
+                let g = g.clone();
+                let mut updates = g.version.subscribe();
+                tokio::spawn(async move {
+                    let mut seen = vec![];
+                    while updates.changed().await.is_ok() {
+                        seen.push(g.tables.read().await[&id].version);
+                        if seen.last() == Some(&UPDATES) {
+                            break;
+                        }
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                    }
+                    seen
+                })
+            })
+            .collect();
+
+        for _ in 0..UPDATES {
+            let mut tables = g.tables.write().await;
+            g.send_update(&mut tables, Some(id));
+            drop(tables);
+            tokio::task::yield_now().await;
+        }
+
+        for consumer in consumers {
+            let seen = consumer.await.unwrap();
+            // Views only move forward, skip the updates made while busy, and end on the latest
+            assert!(seen.windows(2).all(|w| w[0] < w[1]), "{seen:?}");
+            assert!(seen.len() < UPDATES as usize);
+            assert_eq!(seen.last(), Some(&UPDATES));
         }
     }
 }
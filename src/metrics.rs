@@ -0,0 +1,213 @@
+//! Process-wide counters exposed in the Prometheus text format on `/metrics`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        LazyLock, Mutex,
+    },
+    time::Duration,
+};
+
+use scrabble::protocol::ErrorCode;
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Upper bounds of the move validation latency histogram buckets, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.05, 0.1,
+];
+
+#[derive(Default)]
+pub struct Metrics {
+    connections_open: AtomicU64,
+    connections_total: AtomicU64,
+    messages: Mutex<BTreeMap<&'static str, u64>>,
+    invalid_moves: Mutex<BTreeMap<String, u64>>,
+    errors: Mutex<BTreeMap<String, u64>>,
+    validation_latency: Mutex<Histogram>,
+    wordlist_load_seconds: Mutex<f64>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// Counts of tables in each state, gathered at scrape time
+pub struct TableCounts {
+    pub setup: usize,
+    pub running: usize,
+    pub finished: usize,
+}
+
+impl Metrics {
+    pub fn connection_opened(&self) {
+        self.connections_open.fetch_add(1, Relaxed);
+        self.connections_total.fetch_add(1, Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        self.connections_open.fetch_sub(1, Relaxed);
+    }
+
+    pub fn message_received(&self, kind: &'static str) {
+        *self.messages.lock().unwrap().entry(kind).or_insert(0) += 1;
+    }
+
+    pub fn invalid_move(&self, explanation: &str) {
+        let label = explanation_label(explanation);
+        *self.invalid_moves.lock().unwrap().entry(label).or_insert(0) += 1;
+    }
+
+    pub fn error_sent(&self, code: ErrorCode) {
+        *self
+            .errors
+            .lock()
+            .unwrap()
+            .entry(code.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn observe_validation(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let mut h = self.validation_latency.lock().unwrap();
+        h.buckets.resize(LATENCY_BUCKETS.len(), 0);
+        for (bound, n) in LATENCY_BUCKETS.iter().zip(&mut h.buckets) {
+            if secs <= *bound {
+                *n += 1;
+            }
+        }
+        h.sum += secs;
+        h.count += 1;
+    }
+
+    pub fn wordlist_loaded(&self, elapsed: Duration) {
+        *self.wordlist_load_seconds.lock().unwrap() = elapsed.as_secs_f64();
+    }
+
+    /// Render every metric in the Prometheus text exposition format
+    pub fn render(&self, tables: TableCounts) -> String {
+        let mut out = String::new();
+        macro_rules! w { ($($x:tt)*) => { writeln!(out, $($x)*).unwrap() }; }
+
+        w!("# HELP scrabble_connections Open WebSocket connections.");
+        w!("# TYPE scrabble_connections gauge");
+        w!(
+            "scrabble_connections {}",
+            self.connections_open.load(Relaxed)
+        );
+
+        w!("# HELP scrabble_connections_total WebSocket connections accepted since startup.");
+        w!("# TYPE scrabble_connections_total counter");
+        w!(
+            "scrabble_connections_total {}",
+            self.connections_total.load(Relaxed)
+        );
+
+        w!("# HELP scrabble_tables Tables by game state.");
+        w!("# TYPE scrabble_tables gauge");
+        w!("scrabble_tables{{state=\"setup\"}} {}", tables.setup);
+        w!("scrabble_tables{{state=\"running\"}} {}", tables.running);
+        w!("scrabble_tables{{state=\"finished\"}} {}", tables.finished);
+
+        w!("# HELP scrabble_messages_total Client messages received, by type.");
+        w!("# TYPE scrabble_messages_total counter");
+        for (kind, n) in self.messages.lock().unwrap().iter() {
+            w!("scrabble_messages_total{{type=\"{kind}\"}} {n}");
+        }
+
+        w!("# HELP scrabble_invalid_moves_total Rejected moves, by explanation.");
+        w!("# TYPE scrabble_invalid_moves_total counter");
+        for (explanation, n) in self.invalid_moves.lock().unwrap().iter() {
+            w!("scrabble_invalid_moves_total{{explanation=\"{explanation}\"}} {n}");
+        }
+
+        w!("# HELP scrabble_errors_total Errors sent to clients, by code.");
+        w!("# TYPE scrabble_errors_total counter");
+        for (code, n) in self.errors.lock().unwrap().iter() {
+            w!("scrabble_errors_total{{code=\"{code}\"}} {n}");
+        }
+
+        let h = self.validation_latency.lock().unwrap();
+        w!("# HELP scrabble_move_validation_seconds Time spent validating and applying moves.");
+        w!("# TYPE scrabble_move_validation_seconds histogram");
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            let n = h.buckets.get(i).copied().unwrap_or(0);
+            w!("scrabble_move_validation_seconds_bucket{{le=\"{bound}\"}} {n}");
+        }
+        w!(
+            "scrabble_move_validation_seconds_bucket{{le=\"+Inf\"}} {}",
+            h.count
+        );
+        w!("scrabble_move_validation_seconds_sum {}", h.sum);
+        w!("scrabble_move_validation_seconds_count {}", h.count);
+
+        w!("# HELP scrabble_wordlist_load_seconds Time taken to load the word list at startup.");
+        w!("# TYPE scrabble_wordlist_load_seconds gauge");
+        w!(
+            "scrabble_wordlist_load_seconds {}",
+            self.wordlist_load_seconds.lock().unwrap()
+        );
+
+        out
+    }
+}
+
+/// Explanations can quote the offending word, eg "'QZX' is not a word".
+/// Mask quoted text so that each kind of explanation is one label value.
+fn explanation_label(explanation: &str) -> String {
+    let mut label = String::with_capacity(explanation.len());
+    let mut quoted = false;
+    for c in explanation.chars() {
+        match c {
+            '\'' if !quoted => {
+                quoted = true;
+                label.push_str("'_");
+            }
+            '\'' => {
+                quoted = false;
+                label.push('\'');
+            }
+            '"' | '\\' => {}
+            _ if !quoted => label.push(c),
+            _ => {}
+        }
+    }
+    label
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn explanation_labels() {
+        assert_eq!(
+            explanation_label("'QZX' is not a word"),
+            "'_' is not a word"
+        );
+        assert_eq!(
+            explanation_label("That move is not contiguous"),
+            "That move is not contiguous"
+        );
+    }
+
+    #[test]
+    fn render_histogram() {
+        let m = Metrics::default();
+        m.observe_validation(Duration::from_micros(300));
+        m.observe_validation(Duration::from_secs(1));
+        let out = m.render(TableCounts {
+            setup: 1,
+            running: 0,
+            finished: 0,
+        });
+        assert!(out.contains("scrabble_move_validation_seconds_bucket{le=\"0.0005\"} 1\n"));
+        assert!(out.contains("scrabble_move_validation_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("scrabble_tables{state=\"setup\"} 1\n"));
+    }
+}
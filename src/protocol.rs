@@ -0,0 +1,475 @@
+//! The messages exchanged over the WebSocket between the server and its clients, as JSON.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        unseen::{TileOdds, TileQuestion, UnseenTiles},
+        Board, InvalidMove, Move, Player,
+    },
+    table::{
+        GameState, Rules, SeatOrder, Table, TableId, TableListing, WordCheck, WordQuery, WordSearch,
+    },
+};
+
+/// Version of the messages in this module, exchanged in [`ClientHello`] and [`ServerHello`].
+/// Bump it for any change that older clients can't handle, such as renaming a message or field.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// A message from the server. Sent borrowing from the server's state, and read back by clients as a [`ServerUpdate`].
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "protocol.ts", rename = "ServerMessageT")
+)]
+pub enum ServerMessage<'a> {
+    /// The answer to [`ClientMessage::Hello`]
+    Hello(&'a ServerHello),
+    /// The table the client is at, sent whenever it changes, as [`Table::view_for`] the client
+    Table(&'a Table),
+    /// The tables that can be joined, sent to clients not at a table
+    Lobby(&'a [TableListing]),
+    /// The seat the client just took
+    Seat(&'a Seat),
+    /// Why a [`ClientMessage::PlayMove`] wasn't played
+    InvalidMove(&'a InvalidMove),
+    /// The answer to [`ClientMessage::CheckWord`]
+    WordCheck(&'a WordCheck),
+    /// The answer to [`ClientMessage::SearchWords`]
+    WordSearch(&'a WordSearch),
+    /// The answer to [`ClientMessage::UnseenTiles`]
+    UnseenTiles(&'a UnseenTiles),
+    /// The answer to [`ClientMessage::TileOdds`]
+    TileOdds(&'a TileOdds),
+    /// Why a request failed
+    Error(&'a ServerError),
+}
+
+/// The first message a client sends, before anything else.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "ClientHelloT")
+)]
+pub struct ClientHello {
+    /// The [`PROTOCOL_VERSION`] the client was built with
+    pub protocol: u32,
+    /// The commit the client was built from, if it knows, for logging
+    #[serde(default)]
+    pub build: Option<String>,
+}
+
+/// The server's answer to a [`ClientHello`] it can talk to. A client built from a different commit
+/// still works, but can tell its user that a newer version is available.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "ServerHelloT")
+)]
+pub struct ServerHello {
+    /// The server's [`PROTOCOL_VERSION`]
+    pub protocol: u32,
+    /// The commit the server was built from
+    pub build: String,
+}
+
+/// A seat that the client just took. Send the `token` back in [`ClientMessage::ClaimSeat`] to
+/// take the seat again after reconnecting. Only ever sent to the player in the seat.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "SeatT")
+)]
+pub struct Seat {
+    /// The table the seat is at
+    pub table: TableId,
+    /// The name the seat was taken under
+    pub name: String,
+    /// The secret that proves the seat is yours
+    pub token: String,
+}
+
+/// Why the server didn't do what a client asked. The `code` is for programs to act on and the
+/// `message` is for showing to people. The connection stays open unless the error
+/// [isn't recoverable](ErrorCode::is_recoverable).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "ServerErrorT")
+)]
+pub struct ServerError {
+    /// What kind of request failed
+    pub code: ErrorCode,
+    /// What went wrong, for people
+    pub message: String,
+}
+
+/// What kind of request failed, so that clients don't have to match on error messages.
+/// Sent as the variant name, such as `"NotYourTurn"`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "ErrorCodeT")
+)]
+pub enum ErrorCode {
+    /// The client's protocol version isn't the server's, or its first message wasn't `Hello`.
+    /// The server closes the connection, and the client needs updating, eg by reloading the page.
+    Incompatible,
+    /// The message wasn't a valid [`ClientMessage`]
+    BadMessage,
+    /// The request needs the client to be at a table first
+    NotAtTable,
+    /// Creating or joining a table while already at one. Send `LeaveTable` first.
+    AlreadyAtTable,
+    /// No table has that id or invite code, or the table is private and needs its invite code
+    NoSuchTable,
+    /// The request needs the client to have joined the game with `JoinWithName` first
+    NotInGame,
+    /// Only the table's host can do that
+    NotHost,
+    /// Playing or exchanging when it's another player's turn
+    NotYourTurn,
+    /// The game isn't at the right stage for the request, such as starting a game that's running
+    WrongState,
+    /// Every seat at the table is taken or reserved
+    TableFull,
+    /// Someone at the table already has that name
+    NameTaken,
+    /// The request names a player who isn't in the game
+    NoSuchPlayer,
+    /// The table doesn't allow word lookups during your own turn
+    LookupsNotAllowed,
+    /// Any other request the server won't carry out. The message says why.
+    Refused,
+    /// The server was too busy to handle the request in time. Nothing was changed, so it can be sent again.
+    Busy,
+    /// A bug in the server. The request may have been partly carried out, and the server closes the
+    /// connection so that the client reconnects and starts from a fresh view.
+    Internal,
+}
+
+impl ServerError {
+    /// An error of kind `code`, explained by `message`
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ServerError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl ErrorCode {
+    /// Whether the connection can carry on after this error
+    pub fn is_recoverable(self) -> bool {
+        !matches!(self, ErrorCode::Incompatible | ErrorCode::Internal)
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// A message from a client, each one a request for the server to act on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export, export_to = "protocol.ts", rename = "ClientMessageT")
+)]
+pub enum ClientMessage {
+    /// Must be sent first, see [`ClientHello`]
+    Hello(ClientHello),
+    /// Ask for the table the client is at, or the lobby if it isn't at one
+    UpdateMe,
+    /// Create a table and join its game as the host
+    CreateTable {
+        /// The host's name in the game
+        name: String,
+        /// The table's rules, which the host can't change later
+        rules: Rules,
+        /// Whether the table is left out of the lobby and joined with an invite code instead
+        private: bool,
+    },
+    /// Watch a public table, before taking a seat with `JoinWithName`
+    JoinTable(TableId),
+    /// Watch a table by its invite code, which works for private tables too
+    JoinWithInvite(String),
+    /// Stop watching the table, without giving up your seat
+    LeaveTable,
+    /// Start the game, for the host only
+    StartGame,
+    /// Take a free seat under this name
+    JoinWithName(String),
+    /// Take back a seat this client held before, with the token from its [`Seat`]
+    ClaimSeat {
+        /// The name the seat was taken under
+        name: String,
+        /// The seat's token
+        token: String,
+    },
+    /// Hold a seat for the named player, so that it can't be taken by anyone else. Host only.
+    ReserveSeat(String),
+    /// Free a seat held by `ReserveSeat`. Host only.
+    CancelReservation(String),
+    /// Remove a player from the game before it starts. Host only.
+    KickPlayer(String),
+    /// Choose how players are ordered when the game starts. Host only.
+    SetSeatOrder(SeatOrder),
+    /// Take the named player out of the running game. The host can do this for anyone.
+    ForfeitPlayer(String),
+    /// Take the table back to setup, keeping its players. Host only.
+    ResetTable,
+    /// Vote to play again with the same players once the game is over
+    VoteRematch,
+    /// Play a move on your turn
+    PlayMove(Move),
+    /// Exchange your whole rack on your turn
+    ExchangeTiles,
+    /// Ask the other players to undo your most recent move or exchange
+    RequestTakeback,
+    /// Approve (true) or deny (false) another player's takeback request
+    RespondTakeback(bool),
+    /// Ask whether a word is in the table's dictionary
+    CheckWord(String),
+    /// Ask for words in the table's dictionary that fit some letters or a pattern
+    SearchWords(WordQuery),
+    /// Ask which tiles you haven't seen yet: those in the bag or other players' racks
+    UnseenTiles,
+    /// Ask for the chance of drawing a tile or of an opponent holding one
+    TileOdds(TileQuestion),
+}
+
+impl ClientMessage {
+    /// Name of the variant, for logging without the message's contents
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientMessage::Hello(_) => "Hello",
+            ClientMessage::UpdateMe => "UpdateMe",
+            ClientMessage::CreateTable { .. } => "CreateTable",
+            ClientMessage::JoinTable(_) => "JoinTable",
+            ClientMessage::JoinWithInvite(_) => "JoinWithInvite",
+            ClientMessage::LeaveTable => "LeaveTable",
+            ClientMessage::StartGame => "StartGame",
+            ClientMessage::JoinWithName(_) => "JoinWithName",
+            ClientMessage::ClaimSeat { .. } => "ClaimSeat",
+            ClientMessage::ReserveSeat(_) => "ReserveSeat",
+            ClientMessage::CancelReservation(_) => "CancelReservation",
+            ClientMessage::KickPlayer(_) => "KickPlayer",
+            ClientMessage::SetSeatOrder(_) => "SetSeatOrder",
+            ClientMessage::ForfeitPlayer(_) => "ForfeitPlayer",
+            ClientMessage::ResetTable => "ResetTable",
+            ClientMessage::VoteRematch => "VoteRematch",
+            ClientMessage::PlayMove(_) => "PlayMove",
+            ClientMessage::ExchangeTiles => "ExchangeTiles",
+            ClientMessage::RequestTakeback => "RequestTakeback",
+            ClientMessage::RespondTakeback(_) => "RespondTakeback",
+            ClientMessage::CheckWord(_) => "CheckWord",
+            ClientMessage::SearchWords(_) => "SearchWords",
+            ClientMessage::UnseenTiles => "UnseenTiles",
+            ClientMessage::TileOdds(_) => "TileOdds",
+        }
+    }
+}
+
+/// A [`ServerMessage`] as a client receives it.
+#[derive(Debug, Clone, Deserialize)]
+pub enum ServerUpdate {
+    /// See [`ServerMessage::Hello`]
+    Hello(ServerHello),
+    /// See [`ServerMessage::Table`]
+    Table(Box<TableView>),
+    /// See [`ServerMessage::Lobby`]
+    Lobby(Vec<TableListing>),
+    /// See [`ServerMessage::Seat`]
+    Seat(Seat),
+    /// See [`ServerMessage::InvalidMove`]
+    InvalidMove(InvalidMove),
+    /// See [`ServerMessage::WordCheck`]
+    WordCheck(WordCheck),
+    /// See [`ServerMessage::WordSearch`]
+    WordSearch(WordSearch),
+    /// See [`ServerMessage::UnseenTiles`]
+    UnseenTiles(UnseenTiles),
+    /// See [`ServerMessage::TileOdds`]
+    TileOdds(TileOdds),
+    /// See [`ServerMessage::Error`]
+    Error(ServerError),
+}
+
+/// The parts of a [`Table`] that clients show.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableView {
+    /// See [`Table::id`]
+    pub id: TableId,
+    /// See [`Table::version`]
+    pub version: u64,
+    /// See [`Table::game`]
+    pub game: GameView,
+    /// See [`Table::state`]
+    pub state: GameState,
+    /// See [`Table::host`]
+    pub host: String,
+    /// See [`Table::rules`]
+    pub rules: Rules,
+    /// See [`Table::invite_code`]
+    pub invite_code: Option<String>,
+}
+
+/// The parts of a [`Game`](crate::game::Game) that clients show.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GameView {
+    /// The tiles played so far
+    pub board: Board,
+    /// How many tiles are left to draw
+    pub tiles_in_bag: usize,
+    /// Every player in turn order, with only the client's own rack
+    pub players: Vec<Player>,
+    /// Index into `players` of the player whose turn it is
+    pub whose_turn: usize,
+    /// Whether the game is over
+    pub finished: bool,
+}
+
+impl Display for ServerMessage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerMessage::Hello(h) => write!(f, "{h:?}"),
+            ServerMessage::Table(t) => {
+                write!(f, "Table {{ id: {}, state: {:?}, .. }}", t.id, t.state)
+            }
+            ServerMessage::Lobby(ls) => write!(f, "Lobby([{} tables])", ls.len()),
+            ServerMessage::Seat(s) => {
+                write!(f, "Seat {{ table: {}, name: {}, .. }}", s.table, s.name)
+            }
+            ServerMessage::InvalidMove(im) => {
+                write!(f, "InvalidMove {{ explanation: {}, .. }}", im.explanation)
+            }
+            ServerMessage::WordCheck(c) => write!(f, "{c:?}"),
+            ServerMessage::UnseenTiles(_) => write!(f, "UnseenTiles {{ .. }}"),
+            ServerMessage::TileOdds(o) => write!(f, "{o:?}"),
+            ServerMessage::Error(e) => write!(f, "Error({}: {})", e.code, e.message),
+            ServerMessage::WordSearch(s) => {
+                write!(
+                    f,
+                    "WordSearch {{ query: {:?}, [{} words] }}",
+                    s.query,
+                    s.words.len()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn table_round_trip() {
+        let mut table = Table::new(
+            "Alice".into(),
+            Rules {
+                seats: 2,
+                word_lookups: false,
+            },
+            false,
+        )
+        .unwrap();
+        table.seat_player("Bob".into()).unwrap();
+        // Seeded, so that the first player is sure to have a move
+        let mut game = Game::with_seed(1);
+        game.add_player("Alice");
+        game.add_player("Bob");
+        table.game = game;
+        table.start().unwrap();
+        let m = table.game.ranked_moves(&crate::game::equity::LEAVES)[0]
+            .m
+            .clone();
+        table.game.play_move(&m).unwrap();
+
+        let alice = table.view_for(Some("Alice"));
+        let json = serde_json::to_string(&ServerMessage::Table(&alice)).unwrap();
+        let ServerUpdate::Table(view) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected a table");
+        };
+        assert_eq!(view.id, table.id);
+        assert_eq!(view.version, table.version);
+        assert_eq!(view.state, GameState::Running);
+        assert_eq!(view.game.board, *table.game.board());
+        assert_eq!(view.game.tiles_in_bag, table.game.tiles_in_bag());
+        assert_eq!(view.game.whose_turn, 1);
+        let scores = |ps: &[Player]| ps.iter().map(Player::score).collect::<Vec<_>>();
+        assert_eq!(scores(&view.game.players), scores(table.game.players()));
+        // Only Alice's own rack is sent to her
+        for (sent, p) in view.game.players.iter().zip(table.game.players()) {
+            if p.name() == "Alice" {
+                assert_eq!(sent.tiles(), p.tiles());
+            } else {
+                assert!(sent.tiles().is_empty());
+            }
+        }
+        assert!(!json.contains("seed"));
+
+        let msg = ClientMessage::PlayMove(m);
+        let json = serde_json::to_string(&msg).unwrap();
+        let back: ClientMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn error_round_trip() {
+        // Clients match on the code, so its JSON is part of the protocol
+        let error = ServerError::new(ErrorCode::NotYourTurn, "It's not your turn");
+        let json = serde_json::to_string(&ServerMessage::Error(&error)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Error":{"code":"NotYourTurn","message":"It's not your turn"}}"#
+        );
+        let ServerUpdate::Error(back) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected an error");
+        };
+        assert_eq!(back, error);
+    }
+
+    #[test]
+    fn hello_without_build() {
+        let msg: ClientMessage = serde_json::from_str(r#"{"Hello":{"protocol":1}}"#).unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::Hello(ClientHello {
+                protocol: 1,
+                build: None
+            })
+        );
+    }
+
+    #[test]
+    fn web_client_protocol_version() {
+        // The web client's types are generated into protocol.ts, but its version is written by hand
+        let client = include_str!("../web/src/client.ts");
+        let version = format!("export const PROTOCOL_VERSION = {PROTOCOL_VERSION}\n");
+        assert!(
+            client.contains(&version),
+            "web/src/client.ts needs {version}"
+        );
+    }
+}
@@ -0,0 +1,1171 @@
+//! Tables: a game along with its host, rules and lobby state, as hosted by the server.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Result};
+use rand::{
+    distributions::{Alphanumeric, Uniform},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        dawg::Dawg,
+        solve::WORDLIST,
+        unseen::{TileOdds, UnseenTiles},
+        Game, GameRecord, InvalidMove,
+    },
+    protocol::{ClientMessage, ErrorCode, Seat, ServerError},
+};
+
+/// Identifies a table for as long as the server runs
+pub type TableId = u32;
+
+/// Most finished games a table keeps in its archive, dropping the oldest first
+const MAX_ARCHIVED_GAMES: usize = 20;
+/// How long a table nobody is connected to is kept, so that its players can reconnect
+const EMPTY_TABLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// How long a table is kept after its last change, even with connections still open
+const IDLE_TABLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A game along with everything the server needs to know to host it.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TableT")
+)]
+pub struct Table {
+    /// Unique among the server's tables
+    pub id: TableId,
+    /// The server's update count as of the last change to this table. Only ever increases,
+    /// so a client can tell an out of date view of the table from the latest one.
+    #[cfg_attr(test, ts(type = "number"))]
+    pub version: u64,
+    /// The game being set up, played, or just finished
+    pub game: Game,
+    /// Which stage the game is at
+    pub state: GameState,
+    /// Name of the player who created the table. Only they may start the game.
+    pub host: String,
+    /// The rules chosen when the table was created
+    pub rules: Rules,
+    /// The word list used for lookups at the table
+    pub dictionary: Dictionary,
+    /// Names of players that seats are being held for. Removed once that player joins.
+    pub reserved_seats: Vec<String>,
+    /// How players will be ordered when the game starts
+    pub seat_order: SeatOrder,
+    /// Players who want to play again once the game is finished
+    pub rematch_votes: Vec<String>,
+    /// A takeback waiting for the other players to agree
+    pub takeback: Option<TakebackRequest>,
+    /// The last 20 games finished at this table, oldest first
+    pub archive: Vec<ArchivedGame>,
+    /// Private tables are not listed in the lobby and can only be joined with this code.
+    pub invite_code: Option<String>,
+    /// The secret each seated player reclaims their seat with, see [`Table::claim_seat`]
+    #[serde(skip)]
+    seat_tokens: HashMap<String, String>,
+    /// Cloned by every connection at the table, so that an empty table can be told apart
+    #[serde(skip)]
+    presence: Arc<()>,
+    /// When the table last changed, see [`Table::is_abandoned`]
+    #[serde(skip)]
+    last_active: Instant,
+}
+
+/// Where a table is in the life of its game.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "GameStateT")
+)]
+pub enum GameState {
+    /// Players are joining, and the host can change the seating
+    Setup,
+    /// Players are taking turns
+    Running,
+    /// The game is over and players can vote for a rematch.
+    Finished,
+}
+
+/// A player asking to take back their last turn, and the opponents who have agreed so far.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TakebackRequestT")
+)]
+pub struct TakebackRequest {
+    /// Who wants their turn back
+    pub player: String,
+    /// Who has agreed so far
+    pub approvals: Vec<String>,
+}
+
+/// A finished game and each player's final score. Clients are only sent the scores, and the
+/// record of the game is served separately, from `/tables/:id/archive/:number/record`.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "ArchivedGameT")
+)]
+pub struct ArchivedGame {
+    /// Counts the games finished at the table, starting from 1
+    pub number: usize,
+    /// Each player's final score, in turn order
+    pub scores: Vec<(String, i32)>,
+    /// Everything needed to replay the game
+    #[serde(skip)]
+    pub record: GameRecord,
+}
+
+/// Settings chosen by the host when creating a table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "RulesT")
+)]
+pub struct Rules {
+    /// Number of players the game is for, 2 to 4.
+    pub seats: usize,
+    /// Whether players may check words and search the dictionary during their own turn.
+    #[serde(default)]
+    pub word_lookups: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            seats: 4,
+            word_lookups: false,
+        }
+    }
+}
+
+/// How players are seated when the game starts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "SeatOrderT")
+)]
+pub enum SeatOrder {
+    /// Shuffle the players
+    #[default]
+    Random,
+    /// Seat the named players first, in this order, then anyone else in the order they joined.
+    Fixed(Vec<String>),
+}
+
+/// Word list that a table's moves are checked against.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "DictionaryT")
+)]
+pub enum Dictionary {
+    /// The built-in English word list, see [`WORDLIST`]
+    #[default]
+    English,
+}
+
+/// Most words a dictionary search will return
+const MAX_SEARCH_RESULTS: usize = 200;
+/// Most separate `*`s a pattern search may have
+const MAX_PATTERN_STARS: usize = 3;
+
+impl Dictionary {
+    fn wordlist(self) -> &'static Dawg {
+        match self {
+            Dictionary::English => &WORDLIST,
+        }
+    }
+
+    /// Whether `word` is in the dictionary, in any case
+    pub fn is_word(self, word: &str) -> bool {
+        self.wordlist().contains(&word.to_ascii_lowercase())
+    }
+
+    /// Words matching the query, longest first, in uppercase. Patterns with many matches give
+    /// the first ones alphabetically.
+    pub fn search(self, query: &WordQuery) -> Result<Vec<String>> {
+        let (WordQuery::Letters(q) | WordQuery::Pattern(q)) = query;
+        let q = q.trim().to_ascii_lowercase();
+        ensure!(
+            (1..=15).contains(&q.len()),
+            "Searches must be 1 to 15 characters"
+        );
+
+        let mut words = match query {
+            WordQuery::Letters(_) => {
+                ensure!(
+                    q.bytes().all(|c| c.is_ascii_lowercase() || c == b'?'),
+                    "Letters may only contain A to Z and ? for blanks"
+                );
+                ensure!(
+                    q.bytes().filter(|c| *c == b'?').count() <= 2,
+                    "Letters may have at most 2 blanks"
+                );
+                self.wordlist().anagrams(q.as_bytes())
+            }
+            WordQuery::Pattern(_) => {
+                ensure!(
+                    q.bytes()
+                        .all(|c| c.is_ascii_lowercase() || c == b'?' || c == b'*'),
+                    "Patterns may only contain A to Z, ? and *"
+                );
+                // A run of stars matches the same words as a single one
+                let mut pattern = q.into_bytes();
+                pattern.dedup_by(|a, b| *a == b'*' && *b == b'*');
+                ensure!(
+                    pattern.iter().filter(|c| **c == b'*').count() <= MAX_PATTERN_STARS,
+                    "Patterns may have at most {MAX_PATTERN_STARS} *s"
+                );
+                self.wordlist().matching(&pattern, MAX_SEARCH_RESULTS)
+            }
+        };
+
+        words.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        words.truncate(MAX_SEARCH_RESULTS);
+        words.iter_mut().for_each(|w| w.make_ascii_uppercase());
+        Ok(words)
+    }
+}
+
+/// A dictionary search a player can ask for.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "WordQueryT")
+)]
+pub enum WordQuery {
+    /// Words that can be made from some of these letters, with `?` for a blank, eg "AEINRST?"
+    Letters(String),
+    /// Words that fit this pattern, with `?` for any letter and `*` for any number of letters, eg "C?T*"
+    Pattern(String),
+}
+
+/// The answer to whether a word is in a dictionary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "WordCheckT")
+)]
+pub struct WordCheck {
+    /// The word as it was asked about
+    pub word: String,
+    /// Whether it's in the dictionary
+    pub valid: bool,
+}
+
+/// The answer to a dictionary search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "WordSearchT")
+)]
+pub struct WordSearch {
+    /// What was searched for
+    pub query: WordQuery,
+    /// The words found, see [`Dictionary::search`]
+    pub words: Vec<String>,
+}
+
+/// What came of a message handled by [`Table::handle`], for the server to pass on.
+#[derive(Debug, Clone)]
+pub enum TableReply {
+    /// The table changed, so everyone watching it needs a new view
+    Changed,
+    /// Nothing changed and there's nothing to send
+    Unchanged,
+    /// The player took a new seat, which only they should be sent. The table changed too.
+    Seated(Seat),
+    /// The move wasn't played
+    InvalidMove(InvalidMove),
+    /// The answer to a word check
+    WordCheck(WordCheck),
+    /// The player may search this dictionary. Searches can take a while, so running it is left
+    /// to the caller, without holding on to the table.
+    Search(Dictionary, WordQuery),
+    /// The tiles the player hasn't seen
+    UnseenTiles(UnseenTiles),
+    /// The answer to a question about the unseen tiles
+    TileOdds(TileOdds),
+}
+
+impl TableReply {
+    /// Whether everyone at the table needs a new view
+    pub fn changed(&self) -> bool {
+        matches!(self, TableReply::Changed | TableReply::Seated(_))
+    }
+}
+
+/// What the lobby shows about a public table that is still being set up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    test,
+    derive(ts_rs::TS),
+    ts(export_to = "protocol.ts", rename = "TableListingT")
+)]
+pub struct TableListing {
+    /// The table to join
+    pub id: TableId,
+    /// Who created the table
+    pub host: String,
+    /// Players who have taken a seat
+    pub players: Vec<String>,
+    /// How many more seats are held for players who haven't joined yet
+    pub reserved_seats: usize,
+    /// The table's rules
+    pub rules: Rules,
+    /// The table's word list
+    pub dictionary: Dictionary,
+}
+
+impl Table {
+    /// A table in setup with the host already seated. Private tables get an invite code.
+    pub fn new(host: String, rules: Rules, private: bool) -> Result<Self> {
+        ensure!(
+            (2..=4).contains(&rules.seats),
+            "A game must have 2 to 4 seats"
+        );
+
+        let mut game = Game::new();
+        game.add_player(host.clone());
+        let seat_tokens = HashMap::from([(host.clone(), seat_token())]);
+        Ok(Table {
+            id: next_id(),
+            version: 0,
+            game,
+            state: GameState::Setup,
+            host,
+            rules,
+            dictionary: Dictionary::default(),
+            reserved_seats: vec![],
+            seat_order: SeatOrder::default(),
+            rematch_votes: vec![],
+            takeback: None,
+            archive: vec![],
+            invite_code: private.then(invite_code),
+            seat_tokens,
+            presence: Arc::new(()),
+            last_active: Instant::now(),
+        })
+    }
+
+    /// Whether `name` is the table's host
+    pub fn is_host(&self, name: Option<&str>) -> bool {
+        name == Some(self.host.as_str())
+    }
+
+    /// Whether the table is left out of the lobby and needs its invite code to join
+    pub fn is_private(&self) -> bool {
+        self.invite_code.is_some()
+    }
+
+    /// Players may only use the dictionary outside of their own turn, unless the rules allow it.
+    pub fn ensure_lookups_allowed(&self, name: Option<&str>) -> Result<()> {
+        let own_turn = self.state == GameState::Running
+            && name.is_some_and(|name| self.game.is_players_turn(name));
+        ensure!(
+            !own_turn || self.rules.word_lookups,
+            ServerError::new(
+                ErrorCode::LookupsNotAllowed,
+                "This table doesn't allow word lookups during your own turn"
+            )
+        );
+        Ok(())
+    }
+
+    /// Seat a new player during setup, honoring reservations.
+    pub fn seat_player(&mut self, name: String) -> Result<()> {
+        ensure!(
+            self.state == GameState::Setup,
+            ServerError::new(ErrorCode::WrongState, "Game is not in setup")
+        );
+        ensure!(
+            !self.game.has_player(&name),
+            ServerError::new(ErrorCode::NameTaken, "Name is already taken")
+        );
+
+        if let Some(i) = self.reserved_seats.iter().position(|r| *r == name) {
+            self.reserved_seats.remove(i);
+        } else {
+            let taken = self.game.players().len() + self.reserved_seats.len();
+            ensure!(
+                taken < self.rules.seats,
+                ServerError::new(ErrorCode::TableFull, "Game already full")
+            );
+        }
+
+        self.seat_tokens.insert(name.clone(), seat_token());
+        self.game.add_player(name);
+        Ok(())
+    }
+
+    /// The seat `name` holds, with the token that proves it's theirs. Only ever send it to that player.
+    pub fn seat(&self, name: &str) -> Option<Seat> {
+        Some(Seat {
+            table: self.id,
+            name: name.to_owned(),
+            token: self.seat_tokens.get(name)?.clone(),
+        })
+    }
+
+    /// Check that a player taking back their seat, eg after reconnecting, has its token.
+    pub fn claim_seat(&self, name: &str, token: &str) -> Result<()> {
+        ensure!(
+            self.game.has_player(name),
+            ServerError::new(ErrorCode::NoSuchPlayer, "No player with given name")
+        );
+        ensure!(
+            self.seat_tokens.get(name).is_some_and(|t| t == token),
+            ServerError::new(ErrorCode::NameTaken, "That seat belongs to someone else")
+        );
+        Ok(())
+    }
+
+    /// Hold a seat for `name` during setup, as long as one is free
+    pub fn reserve_seat(&mut self, name: String) -> Result<()> {
+        ensure!(
+            self.state == GameState::Setup,
+            ServerError::new(ErrorCode::WrongState, "Game is not in setup")
+        );
+        ensure!(
+            !self.game.has_player(&name) && !self.reserved_seats.contains(&name),
+            "That player already has a seat"
+        );
+        let taken = self.game.players().len() + self.reserved_seats.len();
+        ensure!(
+            taken < self.rules.seats,
+            ServerError::new(ErrorCode::TableFull, "Game already full")
+        );
+        self.reserved_seats.push(name);
+        Ok(())
+    }
+
+    /// Free the seat held for `name`, if there is one
+    pub fn cancel_reservation(&mut self, name: &str) {
+        self.reserved_seats.retain(|r| r != name);
+    }
+
+    /// Remove a player other than the host during setup, invalidating their seat token
+    pub fn kick_player(&mut self, name: &str) -> Result<()> {
+        ensure!(
+            self.state == GameState::Setup,
+            ServerError::new(
+                ErrorCode::WrongState,
+                "Players can only be removed during setup"
+            )
+        );
+        ensure!(name != self.host, "The host can't remove themselves");
+        ensure!(
+            self.game.remove_player(name),
+            ServerError::new(ErrorCode::NoSuchPlayer, "No player with given name")
+        );
+        self.seat_tokens.remove(name);
+        Ok(())
+    }
+
+    /// Choose the seating during setup. A fixed order may only name players at the table.
+    pub fn set_seat_order(&mut self, order: SeatOrder) -> Result<()> {
+        ensure!(
+            self.state == GameState::Setup,
+            ServerError::new(ErrorCode::WrongState, "Game is not in setup")
+        );
+        if let SeatOrder::Fixed(names) = &order {
+            ensure!(
+                names.iter().all(|n| self.game.has_player(n)),
+                "Seat order names a player who isn't at the table"
+            );
+        }
+        self.seat_order = order;
+        Ok(())
+    }
+
+    /// Start the game once at least two players are seated, ordering them by the seat order
+    pub fn start(&mut self) -> Result<()> {
+        ensure!(
+            self.state == GameState::Setup,
+            ServerError::new(ErrorCode::WrongState, "Game is not in setup")
+        );
+        ensure!(
+            self.game.ready_to_play(),
+            ServerError::new(ErrorCode::WrongState, "Game is not ready to play")
+        );
+        match &self.seat_order {
+            SeatOrder::Random => self.game.start_game(true),
+            SeatOrder::Fixed(order) => {
+                self.game.reorder_players(order);
+                self.game.start_game(false);
+            }
+        }
+        self.state = GameState::Running;
+        Ok(())
+    }
+
+    /// Throw away the current game and go back to setup with the same players.
+    pub fn reset(&mut self) {
+        let mut game = Game::new();
+        for p in self.game.players() {
+            game.add_player(p.name());
+        }
+        self.game = game;
+        self.state = GameState::Setup;
+        self.rematch_votes.clear();
+        self.takeback = None;
+    }
+
+    /// Call after every turn that changes the game.
+    pub fn after_turn(&mut self) {
+        self.takeback = None;
+        self.finish_if_over();
+    }
+
+    /// Move to `GameState::Finished` and archive the game if it just ended.
+    fn finish_if_over(&mut self) {
+        if self.state == GameState::Running && self.game.is_finished() {
+            let scores = self
+                .game
+                .players()
+                .iter()
+                .map(|p| (p.name().to_owned(), p.score()))
+                .collect();
+            let number = self.archive.last().map_or(1, |a| a.number + 1);
+            self.archive.push(ArchivedGame {
+                number,
+                scores,
+                record: self.game.record(),
+            });
+            if self.archive.len() > MAX_ARCHIVED_GAMES {
+                self.archive.remove(0);
+            }
+            self.state = GameState::Finished;
+        }
+    }
+
+    /// Ask the other players to let `name` take back their last turn.
+    pub fn request_takeback(&mut self, name: &str) -> Result<()> {
+        ensure!(
+            self.state == GameState::Running,
+            ServerError::new(ErrorCode::WrongState, "Game is not running")
+        );
+        ensure!(
+            self.game.last_turn_player() == Some(name),
+            "You can only take back your own most recent turn"
+        );
+        self.takeback = Some(TakebackRequest {
+            player: name.to_owned(),
+            approvals: vec![],
+        });
+        self.resolve_takeback();
+        Ok(())
+    }
+
+    /// Agree to or refuse the pending takeback as one of the other players. Once everyone still
+    /// playing has agreed, the turn is taken back, and one refusal cancels the request.
+    pub fn respond_takeback(&mut self, name: &str, approve: bool) -> Result<()> {
+        let Some(request) = &mut self.takeback else {
+            bail!(ServerError::new(
+                ErrorCode::WrongState,
+                "Nobody has asked to take back a turn"
+            ));
+        };
+        ensure!(
+            request.player != name,
+            "You can't approve your own takeback"
+        );
+        ensure!(
+            self.game.has_player(name),
+            ServerError::new(ErrorCode::NotInGame, "Not in the game")
+        );
+
+        if approve {
+            if !request.approvals.iter().any(|a| a == name) {
+                request.approvals.push(name.to_owned());
+            }
+            self.resolve_takeback();
+        } else {
+            self.takeback = None;
+        }
+        Ok(())
+    }
+
+    /// Undo the last turn once every opponent still in the game has approved
+    fn resolve_takeback(&mut self) {
+        let Some(request) = &self.takeback else {
+            return;
+        };
+        let approved = self
+            .game
+            .players()
+            .iter()
+            .filter(|p| !p.has_forfeited() && p.name() != request.player)
+            .all(|p| request.approvals.iter().any(|a| a == p.name()));
+        if approved {
+            self.game.undo_last_turn();
+            self.takeback = None;
+        }
+    }
+
+    /// Record that a player wants a rematch. Once everyone still in the game agrees,
+    /// a new game starts with the same players, rules and dictionary.
+    pub fn vote_rematch(&mut self, name: &str) -> Result<()> {
+        ensure!(
+            self.state == GameState::Finished,
+            ServerError::new(ErrorCode::WrongState, "Game is not finished")
+        );
+        let players: Vec<_> = self
+            .game
+            .players()
+            .iter()
+            .filter(|p| !p.has_forfeited())
+            .map(|p| p.name().to_owned())
+            .collect();
+        ensure!(
+            players.iter().any(|p| p == name),
+            ServerError::new(ErrorCode::NotInGame, "Not in the game")
+        );
+        ensure!(players.len() >= 2, "Not enough players left for a rematch");
+
+        if !self.rematch_votes.iter().any(|v| v == name) {
+            self.rematch_votes.push(name.to_owned());
+        }
+
+        if players.iter().all(|p| self.rematch_votes.contains(p)) {
+            let mut game = Game::new();
+            for p in players {
+                game.add_player(p);
+            }
+            self.game = game;
+            self.rematch_votes.clear();
+            self.state = GameState::Setup;
+            self.start()?;
+        }
+        Ok(())
+    }
+
+    /// A handle for each connection at the table to hold for as long as it's there
+    pub fn presence(&self) -> Arc<()> {
+        self.presence.clone()
+    }
+
+    /// Record that the table just changed, so it isn't evicted as idle
+    pub fn touch(&mut self) {
+        self.last_active = Instant::now();
+    }
+
+    /// Whether the table can be evicted as of `now`: it has had nobody connected for a while,
+    /// or hasn't changed in a long time.
+    pub fn is_abandoned(&self, now: Instant) -> bool {
+        let idle = now.saturating_duration_since(self.last_active);
+        let empty = Arc::strong_count(&self.presence) == 1;
+        (empty && idle > EMPTY_TABLE_TIMEOUT) || idle > IDLE_TABLE_TIMEOUT
+    }
+
+    /// Carry out a message from a client at this table, checking that `name`, the player the
+    /// client has a seat as if any, may send it. Messages about finding or leaving a table, and
+    /// about reclaiming seats, are the server's to handle and are refused here.
+    pub fn handle(&mut self, name: Option<&str>, msg: ClientMessage) -> Result<TableReply> {
+        let is_host = self.is_host(name);
+        let player =
+            || name.ok_or_else(|| ServerError::new(ErrorCode::NotInGame, "Not in the game"));
+        macro_rules! ensure_host {
+            ($message:expr) => {
+                ensure!(is_host, ServerError::new(ErrorCode::NotHost, $message))
+            };
+        }
+        macro_rules! ensure_turn {
+            () => {{
+                ensure!(
+                    self.state == GameState::Running,
+                    ServerError::new(ErrorCode::WrongState, "Game is not running")
+                );
+                let name = player()?;
+                ensure!(
+                    self.game.is_players_turn(name),
+                    ServerError::new(ErrorCode::NotYourTurn, "It's not your turn")
+                );
+            }};
+        }
+
+        match msg {
+            ClientMessage::StartGame => {
+                // If two people are in the game start prompt, this can lead to double starts.
+                // Just ignore secondary start messages.
+                if self.state != GameState::Setup {
+                    return Ok(TableReply::Unchanged);
+                }
+                ensure_host!("Only the host can start the game");
+                self.start()?;
+            }
+            ClientMessage::JoinWithName(new_name) => {
+                ensure!(
+                    name.is_none_or(|n| n == new_name),
+                    "Player is already in the game but tried to set a new name"
+                );
+                if self.game.has_player(&new_name) {
+                    // Someone else's seat can only be taken back with its token
+                    ensure!(
+                        name.is_some(),
+                        ServerError::new(ErrorCode::NameTaken, "Name is already taken")
+                    );
+                    return Ok(TableReply::Unchanged);
+                }
+                ensure!(
+                    self.state == GameState::Setup,
+                    ServerError::new(ErrorCode::NoSuchPlayer, "No player with given name")
+                );
+                self.seat_player(new_name.clone())?;
+                let seat = self.seat(&new_name).expect("a new player has a seat");
+                return Ok(TableReply::Seated(seat));
+            }
+            ClientMessage::ReserveSeat(other) => {
+                ensure_host!("Only the host can reserve seats");
+                self.reserve_seat(other)?;
+            }
+            ClientMessage::CancelReservation(other) => {
+                ensure_host!("Only the host can cancel reservations");
+                self.cancel_reservation(&other);
+            }
+            ClientMessage::KickPlayer(other) => {
+                ensure_host!("Only the host can remove players");
+                self.kick_player(&other)?;
+            }
+            ClientMessage::SetSeatOrder(order) => {
+                ensure_host!("Only the host can choose the seat order");
+                self.set_seat_order(order)?;
+            }
+            ClientMessage::ForfeitPlayer(other) => {
+                ensure!(
+                    self.state == GameState::Running,
+                    ServerError::new(ErrorCode::WrongState, "Game is not running")
+                );
+                ensure!(
+                    is_host || name == Some(other.as_str()),
+                    ServerError::new(
+                        ErrorCode::NotHost,
+                        "Only the host can forfeit other players"
+                    )
+                );
+                ensure!(
+                    self.game.forfeit(&other),
+                    ServerError::new(ErrorCode::NoSuchPlayer, "No player with given name")
+                );
+                self.after_turn();
+            }
+            ClientMessage::ResetTable => {
+                ensure_host!("Only the host can reset the table");
+                self.reset();
+            }
+            ClientMessage::VoteRematch => self.vote_rematch(player()?)?,
+            ClientMessage::PlayMove(m) => {
+                ensure_turn!();
+                match self.game.play_move(&m) {
+                    Ok(()) => self.after_turn(),
+                    Err(im) => return Ok(TableReply::InvalidMove(im)),
+                }
+            }
+            ClientMessage::ExchangeTiles => {
+                ensure_turn!();
+                self.game.exchange_tiles();
+                self.after_turn();
+            }
+            ClientMessage::RequestTakeback => self.request_takeback(player()?)?,
+            ClientMessage::RespondTakeback(approve) => self.respond_takeback(player()?, approve)?,
+            ClientMessage::CheckWord(word) => {
+                self.ensure_lookups_allowed(name)?;
+                let valid = self.dictionary.is_word(&word);
+                return Ok(TableReply::WordCheck(WordCheck { word, valid }));
+            }
+            ClientMessage::SearchWords(query) => {
+                self.ensure_lookups_allowed(name)?;
+                return Ok(TableReply::Search(self.dictionary, query));
+            }
+            ClientMessage::UnseenTiles => {
+                return Ok(TableReply::UnseenTiles(self.unseen_tiles(name)?));
+            }
+            ClientMessage::TileOdds(question) => {
+                let chance = self.unseen_tiles(name)?.answer(&question).ok_or_else(|| {
+                    ServerError::new(ErrorCode::NoSuchPlayer, "No opponent with given name")
+                })?;
+                return Ok(TableReply::TileOdds(TileOdds { question, chance }));
+            }
+            ClientMessage::Hello(_)
+            | ClientMessage::UpdateMe
+            | ClientMessage::CreateTable { .. }
+            | ClientMessage::JoinTable(_)
+            | ClientMessage::JoinWithInvite(_)
+            | ClientMessage::LeaveTable
+            | ClientMessage::ClaimSeat { .. } => bail!("{} isn't for a table", msg.kind()),
+        }
+        Ok(TableReply::Changed)
+    }
+
+    /// The tiles the named player hasn't seen in the running or finished game
+    fn unseen_tiles(&self, name: Option<&str>) -> Result<UnseenTiles> {
+        ensure!(
+            self.state != GameState::Setup,
+            ServerError::new(ErrorCode::WrongState, "Game has not started")
+        );
+        let unseen = name
+            .and_then(|name| self.game.unseen_tiles(name))
+            .ok_or_else(|| ServerError::new(ErrorCode::NotInGame, "Not in the game"))?;
+        Ok(unseen)
+    }
+
+    /// The table as `name` is shown it, with only their own rack, see [`Game::hide_racks`].
+    /// Spectators pass `None`.
+    pub fn view_for(&self, name: Option<&str>) -> Table {
+        let mut view = self.clone();
+        view.game.hide_racks(name);
+        view
+    }
+
+    /// What the lobby shows about this table
+    pub fn listing(&self) -> TableListing {
+        TableListing {
+            id: self.id,
+            host: self.host.clone(),
+            players: self
+                .game
+                .players()
+                .iter()
+                .map(|p| p.name().to_owned())
+                .collect(),
+            reserved_seats: self.reserved_seats.len(),
+            rules: self.rules.clone(),
+            dictionary: self.dictionary,
+        }
+    }
+}
+
+fn next_id() -> TableId {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+fn seat_token() -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect()
+}
+
+/// Six letters, avoiding ones that are easy to misread
+fn invite_code() -> String {
+    const LETTERS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ";
+    rand::thread_rng()
+        .sample_iter(Uniform::new(0, LETTERS.len()))
+        .take(6)
+        .map(|i| char::from(LETTERS[i]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reserved_seats_are_held() {
+        let mut t = Table::new(
+            "Alice".into(),
+            Rules {
+                seats: 2,
+                ..Rules::default()
+            },
+            false,
+        )
+        .unwrap();
+        t.reserve_seat("Bob".into()).unwrap();
+        t.seat_player("Charlie".into()).unwrap_err();
+        t.seat_player("Bob".into()).unwrap();
+        assert!(t.reserved_seats.is_empty());
+        assert_eq!(t.game.players().len(), 2);
+    }
+
+    #[test]
+    fn seats_are_claimed_with_tokens() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        let alice = t.seat("Alice").unwrap().token;
+        let bob = t.seat("Bob").unwrap().token;
+        assert_ne!(alice, bob);
+        t.claim_seat("Alice", &alice).unwrap();
+        t.claim_seat("Alice", &bob).unwrap_err();
+        t.claim_seat("Charlie", &bob).unwrap_err();
+
+        t.kick_player("Bob").unwrap();
+        t.claim_seat("Bob", &bob).unwrap_err();
+        assert_eq!(t.seat("Bob"), None);
+    }
+
+    #[test]
+    fn handle_checks_the_sender() {
+        let code =
+            |res: Result<TableReply>| res.unwrap_err().downcast::<ServerError>().unwrap().code;
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        let reply = t.handle(None, ClientMessage::JoinWithName("Bob".into()));
+        assert!(matches!(reply, Ok(TableReply::Seated(Seat { name, .. })) if name == "Bob"));
+        let reply = t.handle(None, ClientMessage::JoinWithName("Bob".into()));
+        assert_eq!(code(reply), ErrorCode::NameTaken);
+        let reply = t.handle(Some("Bob"), ClientMessage::StartGame);
+        assert_eq!(code(reply), ErrorCode::NotHost);
+        assert!(t
+            .handle(Some("Alice"), ClientMessage::StartGame)
+            .unwrap()
+            .changed());
+        assert!(!t
+            .handle(Some("Alice"), ClientMessage::StartGame)
+            .unwrap()
+            .changed());
+
+        let first = t.game.current_player().name().to_owned();
+        let second = if first == "Alice" { "Bob" } else { "Alice" };
+        let reply = t.handle(Some(second), ClientMessage::ExchangeTiles);
+        assert_eq!(code(reply), ErrorCode::NotYourTurn);
+        let reply = t.handle(None, ClientMessage::ExchangeTiles);
+        assert_eq!(code(reply), ErrorCode::NotInGame);
+        let reply = t.handle(Some(&first), ClientMessage::CheckWord("qi".into()));
+        assert_eq!(code(reply), ErrorCode::LookupsNotAllowed);
+        assert!(t
+            .handle(Some(&first), ClientMessage::ExchangeTiles)
+            .unwrap()
+            .changed());
+        let reply = t.handle(Some(&first), ClientMessage::CheckWord("qi".into()));
+        assert!(matches!(
+            reply,
+            Ok(TableReply::WordCheck(WordCheck { valid: true, .. }))
+        ));
+        t.handle(Some(&first), ClientMessage::LeaveTable)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn fuzz_corpus_plays_a_game() {
+        // Replayed the way the fuzz target does, so that a corpus that no longer gets anywhere is caught
+        let mut t = Table::new("P0".into(), Rules::default(), false).unwrap();
+        t.game = Game::with_seed(0);
+        t.game.add_player("P0");
+        let mut states = vec![t.state];
+        for line in include_str!("../fuzz/corpus/client_messages/seed-game").lines() {
+            let (seat, json) = line.split_once(' ').unwrap();
+            let name = format!("P{seat}");
+            let name = t.game.has_player(&name).then_some(name.as_str());
+            let msg = serde_json::from_str(json).unwrap();
+            if let Err(e) = t.handle(name, msg) {
+                panic!("{line}: {e}");
+            }
+            if states.last() != Some(&t.state) {
+                states.push(t.state);
+            }
+        }
+        use GameState::*;
+        assert_eq!(states, [Setup, Running, Finished, Setup]);
+    }
+
+    #[test]
+    fn abandoned_tables() {
+        let t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        let now = Instant::now();
+        let presence = t.presence();
+        assert!(!t.is_abandoned(now + EMPTY_TABLE_TIMEOUT * 2));
+        assert!(t.is_abandoned(now + IDLE_TABLE_TIMEOUT * 2));
+        drop(presence);
+        assert!(!t.is_abandoned(now));
+        assert!(t.is_abandoned(now + EMPTY_TABLE_TIMEOUT * 2));
+    }
+
+    #[test]
+    fn refusals_have_error_codes() {
+        let code = |res: Result<()>| res.unwrap_err().downcast::<ServerError>().map(|e| e.code);
+        let mut t = Table::new(
+            "Alice".into(),
+            Rules {
+                seats: 2,
+                ..Rules::default()
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            code(t.seat_player("Alice".into())).unwrap(),
+            ErrorCode::NameTaken
+        );
+        t.seat_player("Bob".into()).unwrap();
+        assert_eq!(
+            code(t.seat_player("Charlie".into())).unwrap(),
+            ErrorCode::TableFull
+        );
+        // Refusals without a code of their own are sent as `ErrorCode::Refused`
+        code(t.reserve_seat("Bob".into())).unwrap_err();
+        t.start().unwrap();
+        assert_eq!(code(t.start()).unwrap(), ErrorCode::WrongState);
+    }
+
+    #[test]
+    fn fixed_seat_order() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        t.seat_player("Charlie".into()).unwrap();
+        t.kick_player("Alice").unwrap_err();
+        t.set_seat_order(SeatOrder::Fixed(vec!["Daniel".into()]))
+            .unwrap_err();
+        t.set_seat_order(SeatOrder::Fixed(vec!["Charlie".into()]))
+            .unwrap();
+        t.start().unwrap();
+
+        let names: Vec<_> = t.game.players().iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["Charlie", "Alice", "Bob"]);
+
+        t.reset();
+        assert_eq!(t.state, GameState::Setup);
+        assert_eq!(t.game.players().len(), 3);
+    }
+
+    #[test]
+    fn rematch_after_forfeit() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        t.seat_player("Charlie".into()).unwrap();
+        t.start().unwrap();
+
+        t.game.forfeit("Bob");
+        t.after_turn();
+        assert_eq!(t.state, GameState::Running);
+        t.game.set_finished();
+        t.after_turn();
+        assert_eq!(t.state, GameState::Finished);
+        assert_eq!(t.archive.len(), 1);
+
+        t.vote_rematch("Bob").unwrap_err();
+        t.vote_rematch("Alice").unwrap();
+        assert_eq!(t.state, GameState::Finished);
+        t.vote_rematch("Charlie").unwrap();
+        assert_eq!(t.state, GameState::Running);
+        assert_eq!(t.game.players().len(), 2);
+    }
+
+    #[test]
+    fn archive_keeps_recent_scores() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        for _ in 0..MAX_ARCHIVED_GAMES + 5 {
+            t.start().unwrap();
+            t.game.set_finished();
+            t.after_turn();
+            t.reset();
+        }
+        assert_eq!(t.archive.len(), MAX_ARCHIVED_GAMES);
+        assert_eq!(t.archive[0].number, 6);
+        assert_eq!(t.archive[0].record.players.len(), 2);
+
+        // Only the scores are sent to clients
+        let json = serde_json::to_value(&t.archive[0]).unwrap();
+        assert_eq!(json["number"], 6);
+        assert_eq!(json["scores"].as_array().unwrap().len(), 2);
+        assert!(json.get("record").is_none());
+    }
+
+    #[test]
+    fn takeback_needs_every_opponent() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        t.seat_player("Charlie".into()).unwrap();
+        t.set_seat_order(SeatOrder::Fixed(vec![])).unwrap();
+        t.start().unwrap();
+
+        t.request_takeback("Alice").unwrap_err();
+        t.game.exchange_tiles();
+        t.after_turn();
+        t.request_takeback("Bob").unwrap_err();
+        t.request_takeback("Alice").unwrap();
+
+        t.respond_takeback("Alice", true).unwrap_err();
+        t.respond_takeback("Bob", true).unwrap();
+        assert!(t.game.is_players_turn("Bob"));
+        t.respond_takeback("Charlie", true).unwrap();
+        assert!(t.game.is_players_turn("Alice"));
+        assert!(t.takeback.is_none());
+    }
+
+    #[test]
+    fn lookups_outside_own_turn() {
+        let mut t = Table::new("Alice".into(), Rules::default(), false).unwrap();
+        t.seat_player("Bob".into()).unwrap();
+        t.ensure_lookups_allowed(Some("Alice")).unwrap();
+        t.set_seat_order(SeatOrder::Fixed(vec!["Alice".into(), "Bob".into()]))
+            .unwrap();
+        t.start().unwrap();
+        t.ensure_lookups_allowed(Some("Alice")).unwrap_err();
+        t.ensure_lookups_allowed(Some("Bob")).unwrap();
+        t.ensure_lookups_allowed(None).unwrap();
+        t.rules.word_lookups = true;
+        t.ensure_lookups_allowed(Some("Alice")).unwrap();
+    }
+
+    #[test]
+    fn dictionary_search() {
+        let d = Dictionary::English;
+        assert!(d.is_word("Quixotic"));
+        assert!(!d.is_word("qxz"));
+        let words = d.search(&WordQuery::Pattern("c?t".into())).unwrap();
+        assert!(words.contains(&"CAT".to_string()));
+        assert!(words.iter().all(|w| w.len() == 3));
+        let words = d.search(&WordQuery::Letters("AEINRST?".into())).unwrap();
+        assert_eq!(words.len(), MAX_SEARCH_RESULTS);
+        assert!(words[0].len() == 8);
+        d.search(&WordQuery::Letters("??? ".into())).unwrap_err();
+        d.search(&WordQuery::Pattern("".into())).unwrap_err();
+    }
+
+    #[test]
+    fn wildcard_searches_are_bounded() {
+        let d = Dictionary::English;
+        // Runs of stars count as one, and results stop at the limit
+        let words = d.search(&WordQuery::Pattern("**********".into())).unwrap();
+        assert_eq!(words.len(), MAX_SEARCH_RESULTS);
+        let words = d.search(&WordQuery::Pattern("*a*e*".into())).unwrap();
+        assert_eq!(words.len(), MAX_SEARCH_RESULTS);
+        // Few words match this, so the whole wordlist gets walked
+        let words = d.search(&WordQuery::Pattern("*q*j*".into())).unwrap();
+        assert!(!words.is_empty() && words.len() < MAX_SEARCH_RESULTS);
+        assert!(words.iter().all(|w| w.contains('Q') && w.contains('J')));
+        // More stars than that are refused
+        d.search(&WordQuery::Pattern("*a*b*c*".into())).unwrap_err();
+    }
+
+    #[test]
+    fn private_tables_have_invite_codes() {
+        let t = Table::new("Alice".into(), Rules::default(), true).unwrap();
+        assert!(t.is_private());
+        assert_eq!(t.invite_code.unwrap().len(), 6);
+        Table::new(
+            "Alice".into(),
+            Rules {
+                seats: 5,
+                ..Rules::default()
+            },
+            false,
+        )
+        .unwrap_err();
+    }
+}
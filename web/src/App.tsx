@@ -1,9 +1,15 @@
-import { useEffect, useState } from 'react'
+import { useEffect, useRef, useState } from 'react'
 import useWebSocket, { ReadyState } from 'react-use-websocket'
-import { ClientMessageT, ServerMessageT, TableT, serverAddr } from './client'
+import { BUILD, ClientMessageT, PROTOCOL_VERSION, SeatT, ServerMessageT, TableListingT, TableT, UnseenTilesT, WordCheckT, WordSearchT, serverAddr } from './client'
 import { GameView } from './GameView'
 import { MoveT } from './game-types'
 import { SetupView } from './SetupView'
+import { LobbyView } from './LobbyView'
+import { HostControls } from './HostControls'
+import { Rematch } from './Rematch'
+import { Takeback } from './Takeback'
+import { WordLookup } from './WordLookup'
+import { TileTracker } from './TileTracker'
 import { DebugInfo } from './DebugInfo'
 import './App.scss'
 
@@ -14,24 +20,44 @@ const getStoredName = () => localStorage["name"]
 const setStoredName = (name: string) => localStorage["name"] = name
 const delStoredName = () => localStorage.removeItem("name")
 
+// The message that rejoins the table we were last at, so reconnecting goes straight back to it
+const getStoredJoin = (): ClientMessageT | undefined => localStorage["join"] && JSON.parse(localStorage["join"])
+const setStoredJoin = (table: TableT) => localStorage["join"] =
+  JSON.stringify(table.invite_code ? { JoinWithInvite: table.invite_code } : { JoinTable: table.id })
+const delStoredJoin = () => localStorage.removeItem("join")
+
+// The seat we last took, whose token lets us take it back after reconnecting
+const getStoredSeat = (): SeatT | undefined => localStorage["seat"] && JSON.parse(localStorage["seat"])
+const setStoredSeat = (seat: SeatT) => localStorage["seat"] = JSON.stringify(seat)
+
 const App = () => {
+  // Set once the server turns us away, since reconnecting won't help until the page is reloaded
+  const incompatible = useRef(false)
   const { sendJsonMessage, lastJsonMessage, readyState } = useWebSocket(serverAddr, {
     reconnectAttempts: 20,
     reconnectInterval: attemptNumber => attemptNumber < 10 ? 1000 : 5000, // ms
-    shouldReconnect: () => true,
+    shouldReconnect: () => !incompatible.current,
   })
   const sendMessage = (m: ClientMessageT) => sendJsonMessage(m)
 
   const [table, setTable] = useState<TableT | undefined>(undefined)
+  const [lobby, setLobby] = useState<Array<TableListingT> | undefined>(undefined)
   const [name, setName] = useState<string | undefined>(undefined)
+  const [lookup, setLookup] = useState<WordCheckT | WordSearchT | undefined>(undefined)
+  const [unseen, setUnseen] = useState<UnseenTilesT | undefined>(undefined)
 
-  // If readyState changes to OPEN, send a request for an update.
-  // If readyState is anything else, clear the table.
+  // If readyState changes to OPEN, rejoin our last table or ask for the lobby.
+  // If readyState is anything else, clear the table. If the connection closed
+  // before we got back to our stored table, it no longer exists, so forget it.
   useEffect(() => {
     if (readyState === ReadyState.OPEN) {
-      sendMessage("UpdateMe")
+      sendMessage({ Hello: { protocol: PROTOCOL_VERSION, build: BUILD } })
+      sendMessage(getStoredJoin() ?? "UpdateMe")
     } else {
+      if (table === undefined)
+        delStoredJoin()
       setTable(undefined)
+      setLobby(undefined)
       setName(undefined)
     }
   }, [readyState])
@@ -40,10 +66,36 @@ const App = () => {
   useEffect(() => {
     if (lastJsonMessage) {
       const msg = lastJsonMessage as ServerMessageT
-      if ("Table" in msg) {
+      if ("Hello" in msg) {
+        if (msg.Hello.build !== BUILD)
+          console.info(`The server is running build ${msg.Hello.build}, this page is ${BUILD}`)
+      } else if ("Table" in msg) {
+        // Versions only increase, so an older view of our table is out of date
+        if (table !== undefined && table.id === msg.Table.id && msg.Table.version < table.version)
+          return
+        setStoredJoin(msg.Table)
         setTable(msg.Table)
+        setLobby(undefined)
+      } else if ("Lobby" in msg) {
+        delStoredJoin()
+        setTable(undefined)
+        setLobby(msg.Lobby)
+      } else if ("Seat" in msg) {
+        setStoredSeat(msg.Seat)
       } else if ("InvalidMove" in msg) {
         alert(msg.InvalidMove.explanation)
+      } else if ("WordCheck" in msg) {
+        setLookup(msg.WordCheck)
+      } else if ("WordSearch" in msg) {
+        setLookup(msg.WordSearch)
+      } else if ("UnseenTiles" in msg) {
+        setUnseen(msg.UnseenTiles)
+      } else if ("Error" in msg && msg.Error.code === "Incompatible") {
+        incompatible.current = true
+        if (confirm(`${msg.Error.message}. Reload the page?`))
+          window.location.reload()
+      } else if ("Error" in msg) {
+        alert(msg.Error.message)
       } else {
         alert("Unhandled ServerMessage (see console)")
         console.error("Unhandled ServerMessage", msg);
@@ -51,22 +103,28 @@ const App = () => {
     }
   }, [lastJsonMessage])
 
-  // When the user enters their name, send that name to the server and add it to local storage
+  // When the user enters their name at a table, send that name to the server and add it to local storage.
+  // Taking back a seat we held before needs the token the server gave us for it.
   useEffect(() => {
     if (name !== undefined) {
       setStoredName(name)
-      sendMessage({ "JoinWithName": name })
+      const seat = getStoredSeat()
+      if (table !== undefined && seat?.table === table.id && seat.name === name)
+        sendMessage({ "ClaimSeat": { name, token: seat.token } })
+      else if (table !== undefined)
+        sendMessage({ "JoinWithName": name })
     }
   }, [name])
 
   useEffect(() => {
     const storedName = getStoredName()
     if (name === undefined && table !== undefined && storedName !== undefined) {
-      if (table.game.players.some(p => p.name == storedName))
-        // We have a stored name and it's in the game, so use it
+      const seat = getStoredSeat()
+      if (seat?.table === table.id && seat.name === storedName && table.game.players.some(p => p.name == storedName))
+        // We have a stored name and it's our seat in the game, so take it back
         setName(storedName)
       else
-        // We have a stored name but it's not in the game, so it's outdated and needs to be removed
+        // We have a stored name but it's not ours in this game, so it's outdated and needs to be removed
         delStoredName()
     }
   }, [table, name])
@@ -78,15 +136,35 @@ const App = () => {
         if (confirm("Start game for everyone? (only do this once everyone has joined)"))
           sendMessage("StartGame")
       }
-      elems.push(<SetupView key="setup" game={table.game} joinGame={setName} name={name} startGame={startGame} />)
+      const leaveTable = () => {
+        setName(undefined)
+        sendMessage("LeaveTable")
+      }
+      elems.push(<SetupView key="setup" table={table} joinGame={setName} name={name} startGame={startGame} leaveTable={leaveTable} sendMessage={sendMessage} />)
       break;
     case "Running":
+    case "Finished":
       const playMove = (move: MoveT) => sendMessage({ "PlayMove": move })
       const exchangeTiles = () => sendMessage("ExchangeTiles")
       elems.push(<GameView key="game" game={table.game} name={name} playMove={playMove} exchangeTiles={exchangeTiles} />)
+      if (table.state === "Running")
+        elems.push(<Takeback key="takeback" table={table} name={name} sendMessage={sendMessage} />)
+      if (table.state === "Running" && name !== undefined)
+        elems.push(<TileTracker key="tracker" table={table} unseen={unseen} sendMessage={sendMessage} />)
+      // The server refuses lookups during your own turn unless the table allows them
+      const ownTurn = table.state === "Running" && table.game.players[table.game.whose_turn].name === name
+      if (table.rules.word_lookups || !ownTurn)
+        elems.push(<WordLookup key="lookup" result={lookup} sendMessage={sendMessage} />)
+      if (table.state === "Finished")
+        elems.push(<Rematch key="rematch" table={table} name={name} voteRematch={() => sendMessage("VoteRematch")} />)
+      if (name === table.host)
+        elems.push(<HostControls key="host" table={table} sendMessage={sendMessage} />)
       break;
     case undefined:
-      elems.push(<h1 key="notconnected" style={{ color: 'white' }}>Not connected</h1>)
+      if (lobby !== undefined)
+        elems.push(<LobbyView key="lobby" tables={lobby} sendMessage={sendMessage} setName={setName} />)
+      else
+        elems.push(<h1 key="notconnected" style={{ color: 'white' }}>Not connected</h1>)
       break;
   }
 
@@ -104,6 +182,7 @@ const App = () => {
       name,
       storedName: getStoredName(),
       table,
+      lobby,
       lastJsonMessage,
     }
     elems.push(<br key="debugbreak" />)
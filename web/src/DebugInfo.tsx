@@ -5,7 +5,7 @@ import { TileT } from "./game-types"
 export const DebugInfo = ({ data }: { data: Record<string, unknown> }) => {
   const [show, setShow] = useState(false)
   const white = { color: "white" }
-  const blacklist = ["board", "tile_bag", "tiles"]
+  const blacklist = ["board", "tiles_in_bag", "tiles"]
 
   let debugPane
   if (show) {
@@ -0,0 +1,30 @@
+import { ClientMessageT, TableT } from "./client"
+import "./HostControls.scss"
+
+export type HostControlsProps = {
+  table: TableT
+  sendMessage: (m: ClientMessageT) => void
+}
+
+export const HostControls = ({ table, sendMessage }: HostControlsProps) => {
+  const onClickForfeit = (name: string) => {
+    if (confirm(`Remove ${name} from the game? Their tiles go back in the bag.`))
+      sendMessage({ ForfeitPlayer: name })
+  }
+  const onClickReset = () => {
+    if (confirm("End this game for everyone and go back to setup?"))
+      sendMessage("ResetTable")
+  }
+
+  const forfeitButtons = table.game.players
+    .filter(p => !p.forfeited)
+    .map(p => <button key={p.name} onClick={() => onClickForfeit(p.name)}>Forfeit {p.name}</button>)
+
+  return (
+    <div className="host-controls">
+      <h3>Host controls</h3>
+      {forfeitButtons}
+      <button onClick={onClickReset}>Reset Table</button>
+    </div>
+  )
+}
@@ -0,0 +1,66 @@
+import { ClientMessageT, TableListingT } from "./client"
+import "./LobbyView.scss"
+
+export type LobbyViewProps = {
+  tables: Array<TableListingT>
+  sendMessage: (m: ClientMessageT) => void
+  setName: (name: string) => void
+}
+
+const inputValue = (id: string): string => (document.getElementById(id)! as HTMLInputElement).value.trim()
+
+export const LobbyView = ({ tables, sendMessage, setName }: LobbyViewProps) => {
+  const onClickCreate = () => {
+    const name = inputValue("host-name-input")
+    if (!name)
+      return alert("Enter a name")
+    const seats = Number(inputValue("seats-input"))
+    const isPrivate = (document.getElementById("private-input")! as HTMLInputElement).checked
+    const word_lookups = (document.getElementById("lookups-input")! as HTMLInputElement).checked
+    setName(name)
+    sendMessage({ CreateTable: { name, rules: { seats, word_lookups }, private: isPrivate } })
+  }
+
+  const onClickJoinInvite = () => {
+    const code = inputValue("invite-input")
+    if (code)
+      sendMessage({ JoinWithInvite: code })
+  }
+
+  let tableList
+  if (tables.length == 0) {
+    tableList = <h3>No open games</h3>
+  } else {
+    const items = tables.map(t =>
+      <li key={t.id}>
+        {t.host}'s game ({t.players.length + t.reserved_seats}/{t.rules.seats} seats, {t.dictionary})
+        <button onClick={() => sendMessage({ JoinTable: t.id })}>Join</button>
+      </li>
+    )
+    tableList = <ul>{items}</ul>
+  }
+
+  return (
+    <div className="lobby-view">
+      <h1>Open games</h1>
+      {tableList}
+      <h2>Host a game</h2>
+      <div className="lobby-input-area">
+        <input id="host-name-input" placeholder="Your name"></input>
+        <select id="seats-input" defaultValue="4">
+          <option value="2">2 players</option>
+          <option value="3">3 players</option>
+          <option value="4">4 players</option>
+        </select>
+        <label><input id="private-input" type="checkbox" /> Private</label>
+        <label><input id="lookups-input" type="checkbox" /> Allow word lookups on your turn</label>
+        <button onClick={onClickCreate}>Create</button>
+      </div>
+      <h2>Join a private game</h2>
+      <div className="lobby-input-area">
+        <input id="invite-input" placeholder="Invite code"></input>
+        <button onClick={onClickJoinInvite}>Join</button>
+      </div>
+    </div>
+  )
+}
@@ -0,0 +1,32 @@
+import { TableT } from "./client"
+import "./HostControls.scss"
+
+export type RematchProps = {
+  table: TableT
+  name: string | undefined
+  voteRematch: () => void
+}
+
+export const Rematch = ({ table, name, voteRematch }: RematchProps) => {
+  const players = table.game.players.filter(p => !p.forfeited).map(p => p.name)
+  const voted = name !== undefined && table.rematch_votes.includes(name)
+  const waitingOn = players.filter(p => !table.rematch_votes.includes(p))
+
+  let button
+  if (name !== undefined && players.includes(name))
+    button = <button onClick={voteRematch} disabled={voted}>{voted ? "Voted" : "Play Again"}</button>
+
+  const pastGames = table.archive.map(a =>
+    <li key={a.number}>Game {a.number}: {a.scores.map(([n, s]) => `${n} ${s}`).join(", ")}</li>
+  )
+
+  return (
+    <div className="host-controls">
+      <h3>Rematch?</h3>
+      {button}
+      <p>Waiting on: {waitingOn.join(", ") || "nobody"}</p>
+      <h3>Past games</h3>
+      <ul>{pastGames}</ul>
+    </div>
+  )
+}
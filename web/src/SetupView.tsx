@@ -1,11 +1,13 @@
-import { GameT } from "./game-types"
+import { ClientMessageT, TableT } from "./client"
 import "./SetupView.scss"
 
 export type SetupViewProps = {
-  game: GameT
+  table: TableT
   name: string | undefined
   joinGame: (name: string) => void
   startGame: () => void
+  leaveTable: () => void
+  sendMessage: (m: ClientMessageT) => void
 }
 
 const randomName = (): string => {
@@ -23,7 +25,9 @@ const randomName = (): string => {
   return name
 }
 
-export const SetupView = ({ game, name, joinGame, startGame }: SetupViewProps) => {
+export const SetupView = ({ table, name, joinGame, startGame, leaveTable, sendMessage }: SetupViewProps) => {
+  const game = table.game
+  const isHost = name === table.host
   let joinGameArea
   if (name === undefined) {
     const onClickJoin = () => {
@@ -57,21 +61,63 @@ export const SetupView = ({ game, name, joinGame, startGame }: SetupViewProps) =
         thisIsMeButton = <button onClick={() => joinGame(player.name)}>This is me</button>
       else if (name === player.name)
         thisIsYou = "(you)"
-      return <li key={i}>{player.name} {thisIsMeButton} {thisIsYou}</li>
+      const host = player.name === table.host ? "(host)" : undefined
+      let kickButton
+      if (isHost && player.name !== table.host)
+        kickButton = <button onClick={() => sendMessage({ KickPlayer: player.name })}>Remove</button>
+      return <li key={i}>{player.name} {host} {thisIsMeButton} {thisIsYou} {kickButton}</li>
     })
-    playerList = <ul className="setup-view-player-list">{playerListItems}</ul>
+    const reservedItems = table.reserved_seats.map(r => {
+      let cancelButton
+      if (isHost)
+        cancelButton = <button onClick={() => sendMessage({ CancelReservation: r })}>Cancel</button>
+      return <li key={`reserved-${r}`}>{r} (reserved) {cancelButton}</li>
+    })
+    playerList = <ul className="setup-view-player-list">{playerListItems}{reservedItems}</ul>
+  }
+
+  let hostArea
+  if (isHost) {
+    const onClickReserve = () => {
+      const input = (document.getElementById("reserve-input")! as HTMLInputElement).value.trim()
+      if (input)
+        sendMessage({ ReserveSeat: input })
+    }
+    const randomSeats = table.seat_order === "Random"
+    const onChangeRandomSeats = () => sendMessage({
+      SetSeatOrder: randomSeats ? { Fixed: game.players.map(p => p.name) } : "Random"
+    })
+    hostArea = (
+      <>
+        <div className="name-input-area">
+          <input id="reserve-input" placeholder="Name to hold a seat for"></input>
+          <button onClick={onClickReserve}>Reserve Seat</button>
+        </div>
+        <label>
+          <input type="checkbox" checked={randomSeats} onChange={onChangeRandomSeats} />
+          Shuffle seats when the game starts (otherwise, play in the order listed)
+        </label>
+      </>
+    )
   }
 
-  const startGameButtonDisabled = game.players.length < 2
+  let inviteCode
+  if (table.invite_code)
+    inviteCode = <h3>Invite code: {table.invite_code}</h3>
+
+  const startGameButtonDisabled = !isHost || game.players.length < 2
 
   return (
     <div className="setup-view">
       <h1>Game setup</h1>
+      {inviteCode}
       {joinGameArea}
+      {hostArea}
       <br />
       <br />
       <button onClick={startGame} disabled={startGameButtonDisabled}>Start The Game</button>
-      <h2>Players:</h2>
+      <button onClick={leaveTable}>Back to Lobby</button>
+      <h2>Players ({game.players.length + table.reserved_seats.length}/{table.rules.seats}):</h2>
       {playerList}
     </div>
   )
@@ -0,0 +1,41 @@
+import { ClientMessageT, TableT } from "./client"
+import "./HostControls.scss"
+
+export type TakebackProps = {
+  table: TableT
+  name: string | undefined
+  sendMessage: (m: ClientMessageT) => void
+}
+
+export const Takeback = ({ table, name, sendMessage }: TakebackProps) => {
+  const request = table.takeback
+  if (name === undefined)
+    return null
+
+  if (request === null) {
+    if (table.game.last_turn_player !== name)
+      return null
+    return (
+      <div className="host-controls">
+        <button onClick={() => sendMessage("RequestTakeback")}>Take Back My Last Turn</button>
+      </div>
+    )
+  }
+
+  if (request.player === name) {
+    return (
+      <div className="host-controls">
+        <p>Waiting for everyone to approve your takeback...</p>
+      </div>
+    )
+  }
+
+  const approved = request.approvals.includes(name)
+  return (
+    <div className="host-controls">
+      <p>{request.player} wants to take back their last turn.</p>
+      <button onClick={() => sendMessage({ RespondTakeback: true })} disabled={approved}>Allow</button>
+      <button onClick={() => sendMessage({ RespondTakeback: false })}>Deny</button>
+    </div>
+  )
+}
@@ -0,0 +1,26 @@
+import { useEffect } from "react"
+import { ClientMessageT, TableT, UnseenTilesT } from "./client"
+import "./HostControls.scss"
+
+export type TileTrackerProps = {
+  table: TableT
+  unseen: UnseenTilesT | undefined
+  sendMessage: (m: ClientMessageT) => void
+}
+
+// Tiles that aren't on the board or in our rack, refreshed whenever the table changes
+export const TileTracker = ({ table, unseen, sendMessage }: TileTrackerProps) => {
+  useEffect(() => sendMessage("UnseenTiles"), [table])
+
+  if (unseen === undefined)
+    return null
+
+  const tiles = unseen.counts.map(([tile, n]) => `${tile === "Blank" ? "?" : tile}×${n}`).join(" ")
+  const opponents = unseen.opponents.map(([name, n]) => `${name} holds ${n}`).join(", ")
+  return (
+    <div className="host-controls">
+      <p>Unseen tiles: {tiles}</p>
+      <p>{unseen.in_bag} in the bag. {opponents}</p>
+    </div>
+  )
+}
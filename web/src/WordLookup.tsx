@@ -0,0 +1,30 @@
+import { useState } from "react"
+import { ClientMessageT, WordCheckT, WordSearchT } from "./client"
+import "./HostControls.scss"
+
+export type WordLookupProps = {
+  result: WordCheckT | WordSearchT | undefined
+  sendMessage: (m: ClientMessageT) => void
+}
+
+export const WordLookup = ({ result, sendMessage }: WordLookupProps) => {
+  const [text, setText] = useState("")
+  const query = text.trim()
+
+  let resultElem = null
+  if (result !== undefined && "valid" in result) {
+    resultElem = <p>{result.word.toUpperCase()} is {result.valid ? "" : "not "}a word</p>
+  } else if (result !== undefined) {
+    resultElem = <p>{result.words.length == 0 ? "No matches" : result.words.join(" ")}</p>
+  }
+
+  return (
+    <div className="host-controls">
+      <input value={text} onChange={e => setText(e.target.value)} placeholder="Word, letters or pattern" />
+      <button disabled={!query} onClick={() => sendMessage({ CheckWord: query })}>Check</button>
+      <button disabled={!query} onClick={() => sendMessage({ SearchWords: { Letters: query } })}>Anagrams</button>
+      <button disabled={!query} onClick={() => sendMessage({ SearchWords: { Pattern: query } })}>Pattern</button>
+      {resultElem}
+    </div>
+  )
+}
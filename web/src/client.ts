@@ -1,21 +1,16 @@
-import { GameT, InvalidMoveT, MoveT } from "./game-types"
+// Generated from the Rust types by `cargo test`, see protocol.ts
+export type {
+  ArchivedGameT, ClientHelloT, ClientMessageT, DictionaryT, ErrorCodeT, GameStateT, RulesT, SeatOrderT,
+  SeatT, ServerErrorT, ServerHelloT, ServerMessageT, TableListingT, TableT, TakebackRequestT, TileOddsT,
+  TileQuestionT, UnseenTilesT, WordCheckT, WordQueryT, WordSearchT,
+} from "./protocol"
 
 export const serverAddr: string = `ws://${document.location.hostname}:2222/`
 
-export type TableT = {
-  game: GameT
-  state: GameStateT
-}
+// Must match PROTOCOL_VERSION in src/protocol.rs, which a test checks
+export const PROTOCOL_VERSION = 3
 
-export type GameStateT = "Setup" | "Running"
+// The commit this page was built from, see vite.config.ts
+export const BUILD: string = __COMMIT_HASH__
 
-export type ServerMessageT = 
-| { Table: TableT }
-| { InvalidMove: InvalidMoveT }
-
-export type ClientMessageT = 
-| "UpdateMe"
-| "StartGame"
-| { JoinWithName: string }
-| { PlayMove: MoveT }
-| "ExchangeTiles"
+export type TableIdT = number
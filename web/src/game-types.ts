@@ -1,54 +1,17 @@
-export type OptionT<T> = T | null;
+import type { TileT } from "./protocol"
+
+// Generated from the Rust types by `cargo test`, see protocol.ts
+export type {
+  AxisT, BoardT, BoardTileT, GameEndT, GameT, InvalidMoveT, InvalidWordT, LetterT, MoveT, PlayedMoveT,
+  PlayerT, TileT, TurnT,
+} from "./protocol"
 
-export type LetterT = 'A' | 'B' | 'C' | 'D' | 'E' | 'F' | 'G' | 'H' | 'I' | 'J' | 'K' | 'L' | 'M'
-                    | 'N' | 'O' | 'P' | 'Q' | 'R' | 'S' | 'T' | 'U' | 'V' | 'W' | 'X' | 'Y' | 'Z' 
-export type TileT = LetterT | 'Blank'
-export type BoardTileT = LetterT | { 'Blank': LetterT }
+export type OptionT<T> = T | null;
 
 export type ModifierT = "DoubleLetter" | "TripleLetter" | "DoubleWord" | "TripleWord"
 
 export type PositionT = [number, number];
 
-type Array15<T> = [T, T, T, T, T, T, T, T, T, T, T, T, T, T, T]
-export type BoardT = Array15<Array15<OptionT<BoardTileT>>>
-
-export type MoveT = {
-  tiles: Array<[PositionT, BoardTileT]>
-}
-
-export type InvalidMoveT = {
-  explanation: string
-  positions: Array<PositionT>
-}
-
-export type PlayedMoveT = {
-  original_move: MoveT
-  word_values: Array<[string, number]>
-}
-
-export type GameEndT =
-  | { RemainingTiles: Array<TileT> }
-  | { PlayedLastMove: number }
-
-export type TurnT = 
-  | { PlayedMove: PlayedMoveT }
-  | "TilesExchanged"
-  | { GameEnd: GameEndT }
-
-export type PlayerT = {
-  name: string
-  tiles: Array<TileT>
-  turns: Array<TurnT>
-}
-
-export type GameT = {
-  board: BoardT
-  tile_bag: Array<TileT>
-  players: Array<PlayerT>
-  whose_turn: number
-  finished: boolean
-}
-
 // Constants from the game
 export const tileValues: Record<TileT, number> = {
   'A': 1, 'E': 1, 'I': 1, 'O': 1, 'U': 1, 'L': 1, 'N': 1, 'S': 1, 'T': 1, 'R': 1,
@@ -8,7 +8,7 @@ export type GameViewHeaderProps = {
 
 const scoreOfPlayer = (p: PlayerT): number =>
   p.turns.reduce((score, turn): number => {
-    if (turn === "TilesExchanged") {
+    if (turn === "TilesExchanged" || turn === "Forfeited") {
       return score
     } if ("PlayedMove" in turn) {
       const value = turn.PlayedMove.word_values.reduce((subscore, word) => subscore + word[1], 0)
@@ -8,6 +8,8 @@ export type MoveHistoryProps = {
 const turnDescription = (turn: TurnT): string => {
   if (turn === "TilesExchanged") {
     return "exchanged their tiles"
+  } else if (turn === "Forfeited") {
+    return "left the game"
   } if ("PlayedMove" in turn) {
     const words = turn.PlayedMove.word_values.map(([word, _val]) => word.toUpperCase()).join(", ")
     const value = turn.PlayedMove.word_values.reduce((subscore, [_word, val]) => subscore + val, 0)
@@ -25,18 +27,15 @@ const turnDescription = (turn: TurnT): string => {
 
 const turnDescriptions = (game: GameT): Array<string> => {
   const descs = [];
-  let turnIdx = 0;
-  outer: for (;;) {
+  const mostTurns = Math.max(...game.players.map(p => p.turns.length))
+  for (let turnIdx = 0; turnIdx < mostTurns; turnIdx++) {
+    // Players who forfeited stop taking turns, so they may have fewer than everyone else
     for (const player of game.players) {
       if (turnIdx < player.turns.length) {
         const desc = turnDescription(player.turns[turnIdx])
         descs.push(`${player.name} ${desc}`)
-      } else {
-        break outer
       }
     }
-
-    turnIdx++
   }
   descs.reverse()
   return descs
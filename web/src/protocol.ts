@@ -0,0 +1,424 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A finished game and each player's final score. Clients are only sent the scores, and the
+ * record of the game is served separately, from `/tables/:id/archive/:number/record`.
+ */
+export type ArchivedGameT = { 
+/**
+ * Counts the games finished at the table, starting from 1
+ */
+number: number, 
+/**
+ * Each player's final score, in turn order
+ */
+scores: Array<[string, number]>, };
+
+/**
+ * The direction a word is read in. Horizontal words share an x coordinate.
+ */
+export type AxisT = "Horizontal" | "Vertical";
+
+/**
+ * A game board, a 15x15 array of optional `BoardTile`s
+ */
+export type BoardT = [[BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null], [BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null, BoardTileT | null]];
+
+/**
+ * Enum for tiles on the board. Blank tiles hold the `Letter` that they represent on the board.
+ */
+export type BoardTileT = "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" | "L" | "M" | "N" | "O" | "P" | "Q" | "R" | "S" | "T" | "U" | "V" | "W" | "X" | "Y" | "Z" | { "Blank": LetterT };
+
+/**
+ * The first message a client sends, before anything else.
+ */
+export type ClientHelloT = { 
+/**
+ * The [`PROTOCOL_VERSION`] the client was built with
+ */
+protocol: number, 
+/**
+ * The commit the client was built from, if it knows, for logging
+ */
+build: string | null, };
+
+/**
+ * A message from a client, each one a request for the server to act on.
+ */
+export type ClientMessageT = { "Hello": ClientHelloT } | "UpdateMe" | { "CreateTable": { 
+/**
+ * The host's name in the game
+ */
+name: string, 
+/**
+ * The table's rules, which the host can't change later
+ */
+rules: RulesT, 
+/**
+ * Whether the table is left out of the lobby and joined with an invite code instead
+ */
+private: boolean, } } | { "JoinTable": number } | { "JoinWithInvite": string } | "LeaveTable" | "StartGame" | { "JoinWithName": string } | { "ClaimSeat": { 
+/**
+ * The name the seat was taken under
+ */
+name: string, 
+/**
+ * The seat's token
+ */
+token: string, } } | { "ReserveSeat": string } | { "CancelReservation": string } | { "KickPlayer": string } | { "SetSeatOrder": SeatOrderT } | { "ForfeitPlayer": string } | "ResetTable" | "VoteRematch" | { "PlayMove": MoveT } | "ExchangeTiles" | "RequestTakeback" | { "RespondTakeback": boolean } | { "CheckWord": string } | { "SearchWords": WordQueryT } | "UnseenTiles" | { "TileOdds": TileQuestionT };
+
+/**
+ * Word list that a table's moves are checked against.
+ */
+export type DictionaryT = "English";
+
+/**
+ * What kind of request failed, so that clients don't have to match on error messages.
+ * Sent as the variant name, such as `"NotYourTurn"`.
+ */
+export type ErrorCodeT = "Incompatible" | "BadMessage" | "NotAtTable" | "AlreadyAtTable" | "NoSuchTable" | "NotInGame" | "NotHost" | "NotYourTurn" | "WrongState" | "TableFull" | "NameTaken" | "NoSuchPlayer" | "LookupsNotAllowed" | "Refused" | "Busy" | "Internal";
+
+export type GameEndT = { "RemainingTiles": Array<TileT> } | { "PlayedLastMove": number };
+
+/**
+ * Where a table is in the life of its game.
+ */
+export type GameStateT = "Setup" | "Running" | "Finished";
+
+/**
+ * A game from setup to the end: the board, the bag, the players and whose turn it is.
+ * Moves are checked against the rules, so a game is always in a state the rules allow.
+ */
+export type GameT = { board: BoardT, 
+/**
+ * Clients are only told how many tiles are left, not which
+ */
+tiles_in_bag: number, players: Array<PlayerT>, whose_turn: number, finished: boolean, 
+/**
+ * The game as it was before the most recent move or exchange, so that it can be taken back.
+ * Clients only see whose turn that was.
+ */
+last_turn_player: string | null, };
+
+/**
+ * A player-facing message that explains why a move is invalid, along with a set of relevant board positions.
+ */
+export type InvalidMoveT = { 
+/**
+ * What's wrong with the move, for showing to the player
+ */
+explanation: string, 
+/**
+ * Squares to highlight, such as the tiles making up a word that isn't in the dictionary
+ */
+positions: Array<[number, number]>, 
+/**
+ * Empty squares that break up a move that isn't contiguous
+ */
+gaps: Array<[number, number]>, 
+/**
+ * Every non-word the move would form, not just the first one found
+ */
+invalid_words: Array<InvalidWordT>, 
+/**
+ * For single-tile moves, which way the main word was read
+ */
+axis: AxisT | null, };
+
+/**
+ * A string of tiles formed by a move that isn't in the dictionary.
+ */
+export type InvalidWordT = { 
+/**
+ * The tiles as read, in uppercase
+ */
+word: string, 
+/**
+ * The squares the word covers
+ */
+positions: Array<[number, number]>, };
+
+/**
+ * Enum for letters in the alphabet. Does not contain a representation of a Blank tile, it is only alphabetical.
+ * It has the same repr as the corresponding lowercase ascii char.
+ */
+export type LetterT = "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" | "L" | "M" | "N" | "O" | "P" | "Q" | "R" | "S" | "T" | "U" | "V" | "W" | "X" | "Y" | "Z";
+
+/**
+ * A set of tiles at some position on a Scrabble board.
+ * `Move`s are not inherently valid - tiles in the move could overlap ones already on the board,
+ * overlap each other, be at invalid coordinates, contain letters the player doesn't have, etc.
+ */
+export type MoveT = { tiles: Array<[[number, number], BoardTileT]>, };
+
+/**
+ * A move that a player previously played, along with the new words it introduced and their point values.
+ * The value of the whole move is the sum of the words' values.
+ */
+export type PlayedMoveT = { original_move: MoveT, word_values: Array<[string, number]>, };
+
+/**
+ * Someone playing a game: their rack, and every turn they've taken.
+ */
+export type PlayerT = { name: string, tiles: Array<TileT>, turns: Array<TurnT>, forfeited: boolean, };
+
+/**
+ * Settings chosen by the host when creating a table.
+ */
+export type RulesT = { 
+/**
+ * Number of players the game is for, 2 to 4.
+ */
+seats: number, 
+/**
+ * Whether players may check words and search the dictionary during their own turn.
+ */
+word_lookups: boolean, };
+
+/**
+ * How players are seated when the game starts.
+ */
+export type SeatOrderT = "Random" | { "Fixed": Array<string> };
+
+/**
+ * A seat that the client just took. Send the `token` back in [`ClientMessage::ClaimSeat`] to
+ * take the seat again after reconnecting. Only ever sent to the player in the seat.
+ */
+export type SeatT = { 
+/**
+ * The table the seat is at
+ */
+table: number, 
+/**
+ * The name the seat was taken under
+ */
+name: string, 
+/**
+ * The secret that proves the seat is yours
+ */
+token: string, };
+
+/**
+ * Why the server didn't do what a client asked. The `code` is for programs to act on and the
+ * `message` is for showing to people. The connection stays open unless the error
+ * [isn't recoverable](ErrorCode::is_recoverable).
+ */
+export type ServerErrorT = { 
+/**
+ * What kind of request failed
+ */
+code: ErrorCodeT, 
+/**
+ * What went wrong, for people
+ */
+message: string, };
+
+/**
+ * The server's answer to a [`ClientHello`] it can talk to. A client built from a different commit
+ * still works, but can tell its user that a newer version is available.
+ */
+export type ServerHelloT = { 
+/**
+ * The server's [`PROTOCOL_VERSION`]
+ */
+protocol: number, 
+/**
+ * The commit the server was built from
+ */
+build: string, };
+
+/**
+ * A message from the server. Sent borrowing from the server's state, and read back by clients as a [`ServerUpdate`].
+ */
+export type ServerMessageT = { "Hello": ServerHelloT } | { "Table": TableT } | { "Lobby": Array<TableListingT> } | { "Seat": SeatT } | { "InvalidMove": InvalidMoveT } | { "WordCheck": WordCheckT } | { "WordSearch": WordSearchT } | { "UnseenTiles": UnseenTilesT } | { "TileOdds": TileOddsT } | { "Error": ServerErrorT };
+
+/**
+ * What the lobby shows about a public table that is still being set up.
+ */
+export type TableListingT = { 
+/**
+ * The table to join
+ */
+id: number, 
+/**
+ * Who created the table
+ */
+host: string, 
+/**
+ * Players who have taken a seat
+ */
+players: Array<string>, 
+/**
+ * How many more seats are held for players who haven't joined yet
+ */
+reserved_seats: number, 
+/**
+ * The table's rules
+ */
+rules: RulesT, 
+/**
+ * The table's word list
+ */
+dictionary: DictionaryT, };
+
+/**
+ * A game along with everything the server needs to know to host it.
+ */
+export type TableT = { 
+/**
+ * Unique among the server's tables
+ */
+id: number, 
+/**
+ * The server's update count as of the last change to this table. Only ever increases,
+ * so a client can tell an out of date view of the table from the latest one.
+ */
+version: number, 
+/**
+ * The game being set up, played, or just finished
+ */
+game: GameT, 
+/**
+ * Which stage the game is at
+ */
+state: GameStateT, 
+/**
+ * Name of the player who created the table. Only they may start the game.
+ */
+host: string, 
+/**
+ * The rules chosen when the table was created
+ */
+rules: RulesT, 
+/**
+ * The word list used for lookups at the table
+ */
+dictionary: DictionaryT, 
+/**
+ * Names of players that seats are being held for. Removed once that player joins.
+ */
+reserved_seats: Array<string>, 
+/**
+ * How players will be ordered when the game starts
+ */
+seat_order: SeatOrderT, 
+/**
+ * Players who want to play again once the game is finished
+ */
+rematch_votes: Array<string>, 
+/**
+ * A takeback waiting for the other players to agree
+ */
+takeback: TakebackRequestT | null, 
+/**
+ * The last 20 games finished at this table, oldest first
+ */
+archive: Array<ArchivedGameT>, 
+/**
+ * Private tables are not listed in the lobby and can only be joined with this code.
+ */
+invite_code: string | null, };
+
+/**
+ * A player asking to take back their last turn, and the opponents who have agreed so far.
+ */
+export type TakebackRequestT = { 
+/**
+ * Who wants their turn back
+ */
+player: string, 
+/**
+ * Who has agreed so far
+ */
+approvals: Array<string>, };
+
+/**
+ * The answer to a [`TileQuestion`].
+ */
+export type TileOddsT = { 
+/**
+ * What was asked
+ */
+question: TileQuestionT, 
+/**
+ * The probability, from 0 to 1
+ */
+chance: number, };
+
+/**
+ * A probability a player can ask about.
+ */
+export type TileQuestionT = { "Draw": { 
+/**
+ * The tile hoped for
+ */
+tile: TileT, 
+/**
+ * How many tiles are drawn
+ */
+draws: number, } } | { "Holds": { 
+/**
+ * The tile asked about
+ */
+tile: TileT, 
+/**
+ * The opponent asked about
+ */
+player: string, } };
+
+/**
+ * Enum for Scrabble tiles that a player is holding. Contains all letters plus a Blank variant.
+ * Like `Letter`, it is repr'd by corresponding ascii chars, while Blank is repr'd as `b'*'`.
+ */
+export type TileT = "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" | "L" | "M" | "N" | "O" | "P" | "Q" | "R" | "S" | "T" | "U" | "V" | "W" | "X" | "Y" | "Z" | "Blank";
+
+export type TurnT = { "PlayedMove": PlayedMoveT } | "TilesExchanged" | "Forfeited" | { "GameEnd": GameEndT };
+
+/**
+ * The tiles a player can't see: everything not on the board or in their own rack.
+ * Each of these is either in the bag or in an opponent's rack.
+ */
+export type UnseenTilesT = { 
+/**
+ * How many of each tile are unseen, A to Z then blanks, skipping tiles with none left
+ */
+counts: Array<[TileT, number]>, 
+/**
+ * How many of them are in the bag
+ */
+in_bag: number, 
+/**
+ * How many tiles each opponent is holding
+ */
+opponents: Array<[string, number]>, };
+
+/**
+ * The answer to whether a word is in a dictionary.
+ */
+export type WordCheckT = { 
+/**
+ * The word as it was asked about
+ */
+word: string, 
+/**
+ * Whether it's in the dictionary
+ */
+valid: boolean, };
+
+/**
+ * A dictionary search a player can ask for.
+ */
+export type WordQueryT = { "Letters": string } | { "Pattern": string };
+
+/**
+ * The answer to a dictionary search.
+ */
+export type WordSearchT = { 
+/**
+ * What was searched for
+ */
+query: WordQueryT, 
+/**
+ * The words found, see [`Dictionary::search`]
+ */
+words: Array<string>, };
@@ -1 +1,4 @@
 /// <reference types="vite/client" />
+
+// The commit the page was built from, defined in vite.config.ts
+declare const __COMMIT_HASH__: string
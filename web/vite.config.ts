@@ -1,5 +1,9 @@
 import { defineConfig } from 'vite'
 import react from '@vitejs/plugin-react'
+import { execSync } from 'child_process'
+
+// Sent to the server when connecting, which tells us its own build the same way (see build.rs)
+const commitHash = execSync('git rev-parse --short HEAD').toString().trim()
 
 // https://vitejs.dev/config/
 export default defineConfig({
@@ -9,4 +13,7 @@ export default defineConfig({
     }
   },
   plugins: [react()],
+  define: {
+    __COMMIT_HASH__: JSON.stringify(commitHash),
+  },
 })